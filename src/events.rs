@@ -1,9 +1,32 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::systems::{BoardState, Piece, PieceState};
+
 /// Events over the wire
 #[derive(Deserialize, Serialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum NetworkEvent {
     GameRx(TetrisIn),
+    /// ask our opponent to pause (true) or resume (false)
+    PauseRequest(bool),
+    /// our opponent agreed, so we both pause or resume now
+    PauseAccept(bool),
+    /// our opponent went back to their menu, so our game is over
+    Leave,
+    /// the client is connected and waiting for the server to schedule our start
+    Ready,
+    /// the server measuring our round trip time, answered with a matching `Pong`
+    Ping(u32),
+    Pong(u32),
+    /// start counting down, the game begins after this many milliseconds
+    StartIn(u32),
+    /// the server's round has moved this far into margin time
+    MarginStep(usize),
+    /// the server decided who won our round, counting from the client's side, `None` is a draw
+    RoundOver(Option<usize>),
+    /// garbage our opponent sent us, already cancelled and scaled by their handicap and margin time
+    Attack(usize),
 }
 
 /// Events coming into our game
@@ -13,6 +36,13 @@ pub enum TetrisIn {
     Tick,
     AddRows(usize),
     User(UserInput),
+    /// hand our board and falling piece over with `TetrisOut::Board`, pausing until `PutBoard`
+    TakeBoard,
+    PutBoard(BoardState),
+    /// how many garbage rows are waiting to enter our board, shown on our meter
+    PendingRows(usize),
+    /// a placement to outline on our board for our current piece, cleared once it locks
+    Outline(Option<Piece>),
 }
 
 #[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -25,6 +55,13 @@ pub enum UserInput {
     Hold,
 }
 
+/// A player's input and when it happened, on the clock `Time::absolute_time` keeps
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct TimedInput {
+    pub input: UserInput,
+    pub at: Duration,
+}
+
 /// Events coming out of our game
 #[derive(Deserialize, Serialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum TetrisOut {
@@ -32,5 +69,8 @@ pub enum TetrisOut {
     ValidIn(TetrisIn),
     LockedPiece,
     RemovedRows(usize),
+    Board(BoardState),
+    /// a new piece is falling, for anyone playing without looking at the screen
+    Spawned(PieceState),
     Lose,
 }
@@ -1,47 +0,0 @@
-use amethyst::core::Transform;
-use amethyst::input::VirtualKeyCode;
-use amethyst::input::{is_close_requested, is_key_down};
-use amethyst::prelude::*;
-use amethyst::renderer::Camera;
-use amethyst::window::ScreenDimensions;
-use log::info;
-
-pub struct GameState;
-
-impl SimpleState for GameState {
-    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        info!("starting");
-
-        let StateData { world, .. } = data;
-
-        let dimensions = (*world.read_resource::<ScreenDimensions>()).clone();
-
-        init_camera(world, &dimensions);
-    }
-
-    fn handle_event(
-        &mut self,
-        _data: StateData<'_, GameData<'_, '_>>,
-        event: StateEvent,
-    ) -> SimpleTrans {
-        // let StateData { world, .. } = data;
-        if let StateEvent::Window(event) = &event {
-            // Check if the window should be closed
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-                return Trans::Quit;
-            }
-        }
-        Trans::None
-    }
-}
-
-fn init_camera(world: &mut World, dimensions: &ScreenDimensions) {
-    let mut transform = Transform::default();
-    transform.set_translation_xyz(dimensions.width() * 0.5, dimensions.height() * 0.5, 1.);
-
-    world
-        .create_entity()
-        .with(Camera::standard_2d(dimensions.width(), dimensions.height()))
-        .with(transform)
-        .build();
-}
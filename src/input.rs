@@ -1,12 +1,27 @@
 use core::fmt;
+use std::path::Path;
 
+use amethyst::config::Config;
 use amethyst::input::BindingTypes;
+use log::warn;
 use serde::{Deserialize, Serialize};
 
 use crate::events::UserInput;
 
-#[derive(Deserialize, Serialize, Clone, Debug, Hash, PartialEq, Eq)]
-pub enum GameAxis {}
+/// Analog sticks, each belonging to a player counted from 0
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum GameAxis {
+    Horizontal(usize),
+    /// positive is down, like the sticks SDL gives us
+    Vertical(usize),
+}
+
+impl GameAxis {
+    /// the horizontal and vertical axes a player moves with
+    pub fn player_axes(player: usize) -> (GameAxis, GameAxis) {
+        (GameAxis::Horizontal(player), GameAxis::Vertical(player))
+    }
+}
 
 impl fmt::Display for GameAxis {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -14,81 +29,30 @@ impl fmt::Display for GameAxis {
     }
 }
 
+/// Everything we can bind, each player's moves are bound separately so any number can share our input
 #[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum GameActions {
-    Left,
-    Right,
-    RotateClockwise,
-    DropSoft,
-    DropHard,
-    Hold,
-
-    OneDropHard,
-    OneHold,
-
-    TwoLeft,
-    TwoRight,
-    TwoRotateClockwise,
-    TwoDropSoft,
-    TwoDropHard,
-    TwoHold,
+    /// a move for the player with this index, counted from 0
+    Player(usize, UserInput),
+    /// extra keys player one only has when playing alone, they sit on player two's half of our keyboard
+    Solo(UserInput),
 
     Debug,
 }
 
 impl GameActions {
-    pub fn single(&self) -> bool {
+    /// which player this action belongs to when we have `player_count` local players
+    pub fn player(&self, player_count: usize) -> Option<usize> {
         match self {
-            GameActions::Left
-            | GameActions::Right
-            | GameActions::RotateClockwise
-            | GameActions::DropSoft
-            | GameActions::DropHard
-            | GameActions::Hold => true,
-            _ => false,
+            GameActions::Player(player, _) if *player < player_count.max(1) => Some(*player),
+            GameActions::Solo(_) if player_count <= 1 => Some(0),
+            _ => None,
         }
     }
 
-    pub fn one(&self) -> bool {
+    pub fn input(&self) -> Option<UserInput> {
         match self {
-            GameActions::Left
-            | GameActions::Right
-            | GameActions::RotateClockwise
-            | GameActions::DropSoft
-            | GameActions::OneDropHard
-            | GameActions::OneHold => true,
-            _ => false,
-        }
-    }
-
-    pub fn two(&self) -> bool {
-        match self {
-            GameActions::TwoLeft
-            | GameActions::TwoRight
-            | GameActions::TwoRotateClockwise
-            | GameActions::TwoDropSoft
-            | GameActions::TwoDropHard
-            | GameActions::TwoHold => true,
-            _ => false,
-        }
-    }
-}
-
-impl Into<Option<UserInput>> for &GameActions {
-    fn into(self) -> Option<UserInput> {
-        match self {
-            GameActions::Left | GameActions::TwoLeft => Some(UserInput::Left),
-            GameActions::Right | GameActions::TwoRight => Some(UserInput::Right),
-            GameActions::RotateClockwise | GameActions::TwoRotateClockwise => {
-                Some(UserInput::RotateClockwise)
-            }
-            GameActions::DropSoft | GameActions::TwoDropSoft => Some(UserInput::DropSoft),
-            GameActions::DropHard | GameActions::OneDropHard | GameActions::TwoDropHard => {
-                Some(UserInput::DropHard)
-            }
-            GameActions::Hold | GameActions::OneHold | GameActions::TwoHold => {
-                Some(UserInput::Hold)
-            }
+            GameActions::Player(_, input) | GameActions::Solo(input) => Some(*input),
             GameActions::Debug => None,
         }
     }
@@ -107,3 +71,106 @@ impl BindingTypes for GameInput {
     type Axis = GameAxis;
     type Action = GameActions;
 }
+
+/// How a player's held keys repeat, everyone sharing a keyboard gets their own
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Handling {
+    /// delayed auto shift, the seconds a side key is held before it starts repeating
+    pub das: f32,
+    /// auto repeat rate, the seconds between repeated side moves, 0 moves straight to the wall
+    pub arr: f32,
+    /// a held soft drop falls this many times faster than level 1 gravity, which is a row a second
+    pub soft_drop_factor: f32,
+    /// the seconds a repeating side key waits after we rotate, hard drop or hold
+    pub das_cut_delay: f32,
+    pub opposing_keys: OpposingKeys,
+}
+
+/// What happens when we hold left and right at the same time
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpposingKeys {
+    /// whichever we pressed last moves us, letting go of it hands over to the other
+    LastPressed,
+    /// we stay put until one of them is let go
+    Cancel,
+}
+
+impl OpposingKeys {
+    pub fn other(self) -> OpposingKeys {
+        match self {
+            OpposingKeys::LastPressed => OpposingKeys::Cancel,
+            OpposingKeys::Cancel => OpposingKeys::LastPressed,
+        }
+    }
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            // the official guide recommends 0.3 but that seems slow
+            das: 0.3,
+            // the official guide recommends moving across the board in 0.5 seconds but that seems slow
+            arr: 0.5 / 10.,
+            soft_drop_factor: 20.,
+            das_cut_delay: 0.,
+            opposing_keys: OpposingKeys::LastPressed,
+        }
+    }
+}
+
+/// Our handling for each player in order, stored as RON next to our other config
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct HandlingSettings {
+    players: Vec<Handling>,
+}
+
+impl HandlingSettings {
+    /// load our handling, everyone gets the default if the file is missing or unreadable
+    pub fn open<P: AsRef<Path>>(path: P) -> HandlingSettings {
+        if !path.as_ref().exists() {
+            return HandlingSettings::default();
+        }
+
+        <HandlingSettings as Config>::load(path).unwrap_or_else(|e| {
+            warn!("Using the default handling: {}", e);
+            HandlingSettings::default()
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) {
+        if let Err(e) = self.write(path) {
+            warn!("Couldn't save our handling: {}", e);
+        }
+    }
+
+    pub fn player(&self, index: usize) -> Handling {
+        self.players.get(index).copied().unwrap_or_default()
+    }
+
+    /// change a player's handling, filling in defaults for anyone before them
+    pub fn player_mut(&mut self, index: usize) -> &mut Handling {
+        if self.players.len() <= index {
+            self.players.resize(index + 1, Handling::default());
+        }
+
+        &mut self.players[index]
+    }
+}
+
+/// Whether we show each player's held actions and recent inputs beside their board, for anyone watching
+#[derive(Copy, Clone, Debug)]
+pub struct InputDisplay {
+    pub enabled: bool,
+    /// how many of our latest inputs we list
+    pub history: usize,
+}
+
+impl Default for InputDisplay {
+    fn default() -> Self {
+        InputDisplay {
+            enabled: false,
+            history: 8,
+        }
+    }
+}
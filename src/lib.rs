@@ -1,9 +1,9 @@
 use crossbeam::channel::Sender;
 
-pub use game_state::GameState;
+pub use states::{GameOver, MenuState, PlayArea};
 
-mod game_state;
 pub mod sprite_loader;
+mod states;
 
 pub mod events;
 pub mod input;
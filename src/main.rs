@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use amethyst::core::frame_limiter::FrameRateLimitStrategy;
 use amethyst::core::TransformBundle;
 use amethyst::input::InputBundle;
@@ -5,17 +7,21 @@ use amethyst::renderer::plugins::RenderToWindow;
 use amethyst::renderer::types::DefaultBackend;
 use amethyst::renderer::RenderFlat2D;
 use amethyst::renderer::RenderingBundle;
+use amethyst::ui::{RenderUi, UiBundle};
 use amethyst::utils::application_root_dir;
 use amethyst::{Application, GameDataBuilder, Logger, LoggerConfig};
 use chrono::Local;
-use clap::{App, AppSettings, Arg, SubCommand};
-use log::LevelFilter;
+use clap::{App, Arg, SubCommand};
+use log::{warn, LevelFilter};
 
-use tetris_for_two::input::GameInput;
+use tetris_for_two::input::{GameInput, HandlingSettings, InputDisplay};
 use tetris_for_two::sprite_loader::SpriteLoaderDesc;
 use tetris_for_two::systems::utils::WithKnownSystemDesc;
-use tetris_for_two::systems::GameType;
-use tetris_for_two::GameState;
+use tetris_for_two::systems::{
+    GameType, GarbageTiming, Hints, MarginTime, MatchConfig, SwapTrigger, TargetPolicy,
+    MAX_LOCAL_PLAYERS,
+};
+use tetris_for_two::MenuState;
 
 fn main() -> amethyst::Result<()> {
     let mut logger_config = LoggerConfig::default();
@@ -40,36 +46,221 @@ fn main() -> amethyst::Result<()> {
 
     let display_config_path = config_dir.join("display.ron");
     let key_bindings_path = config_dir.join("input.ron");
+    let hints_path = config_dir.join("hint.ron");
+    let handling_path = config_dir.join("handling.ron");
 
     let matches = App::new("tetris-for-two")
-        .setting(AppSettings::SubcommandRequiredElseHelp)
-        .subcommand(SubCommand::with_name("server").arg(Arg::with_name("address").required(true)))
-        .subcommand(SubCommand::with_name("client").arg(Arg::with_name("address").required(true)))
+        .arg(
+            Arg::with_name("best-of")
+                .long("best-of")
+                .takes_value(true)
+                .global(true)
+                .default_value("3")
+                .help("How many rounds are in a versus set"),
+        )
+        .arg(
+            Arg::with_name("margin-delay")
+                .long("margin-delay")
+                .takes_value(true)
+                .global(true)
+                .default_value("60")
+                .help("Seconds into a versus round before gravity and attacks start ramping up"),
+        )
+        .arg(
+            Arg::with_name("garbage-delay")
+                .long("garbage-delay")
+                .takes_value(true)
+                .global(true)
+                .default_value("0.5")
+                .help("Seconds incoming garbage waits before it can enter a versus board"),
+        )
+        .arg(
+            Arg::with_name("hints")
+                .long("hints")
+                .global(true)
+                .help("Outline a suggested placement for every piece"),
+        )
+        .arg(
+            Arg::with_name("input-display")
+                .long("input-display")
+                .global(true)
+                .help(
+                    "Show each player's held actions, DAS charge and recent inputs by their board",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("server")
+                .arg(Arg::with_name("address").required(true))
+                .arg(bot_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("client")
+                .arg(Arg::with_name("address").required(true))
+                .arg(bot_arg()),
+        )
         .subcommand(SubCommand::with_name("single"))
-        .subcommand(SubCommand::with_name("double"))
+        .subcommand(SubCommand::with_name("trainer"))
+        .subcommand(
+            SubCommand::with_name("double")
+                .arg(
+                    Arg::with_name("players")
+                        .long("players")
+                        .takes_value(true)
+                        .default_value("2")
+                        .validator(player_count)
+                        .help("How many local players are battling, up to 4"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .takes_value(true)
+                        .possible_values(&["random", "rotating", "attackers"])
+                        .default_value("rotating")
+                        .help("Who receives the garbage from each attack"),
+                )
+                .arg(
+                    Arg::with_name("bots")
+                        .long("bots")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("How many players are CPU opponents, they take the last slots"),
+                )
+                .arg(
+                    Arg::with_name("tbp")
+                        .long("tbp")
+                        .takes_value(true)
+                        .help("Run this Tetris Bot Protocol bot for the CPU opponents"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("survival"))
+        .subcommand(SubCommand::with_name("coop"))
+        .subcommand(
+            SubCommand::with_name("relay").arg(
+                Arg::with_name("pieces")
+                    .long("pieces")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("How many pieces each player places before switching"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("swap")
+                .arg(
+                    Arg::with_name("seconds")
+                        .long("seconds")
+                        .takes_value(true)
+                        .conflicts_with("locks")
+                        .validator(above_zero::<f32>)
+                        .help("Swap boards every N seconds (default 30)"),
+                )
+                .arg(
+                    Arg::with_name("locks")
+                        .long("locks")
+                        .takes_value(true)
+                        .validator(above_zero::<usize>)
+                        .help("Swap boards every N pieces"),
+                ),
+        )
         .get_matches();
 
     let game_type = match matches.subcommand() {
         ("server", Some(sub_matches)) => {
             let address = sub_matches.value_of("address").unwrap();
-            GameType::Server(address.to_string())
+            Some(GameType::Server {
+                address: address.to_string(),
+                bot: sub_matches.is_present("bot"),
+            })
         }
         ("client", Some(sub_matches)) => {
             let address = sub_matches.value_of("address").unwrap();
-            GameType::Client(address.parse().expect("should parse"))
+            Some(GameType::Client {
+                address: address.parse().expect("should parse"),
+                bot: sub_matches.is_present("bot"),
+            })
         }
-        ("single", _) => GameType::Single,
-        ("double", _) => GameType::Double,
-        _ => unreachable!(),
+        ("single", _) => Some(GameType::Single),
+        ("trainer", _) => Some(GameType::Trainer),
+        ("double", Some(sub_matches)) => {
+            let players: usize = sub_matches
+                .value_of("players")
+                .unwrap()
+                .parse()
+                .expect("should parse");
+            let target = sub_matches.value_of("target").unwrap();
+            let bots = sub_matches.value_of("bots").unwrap();
+            let tbp_command = sub_matches.value_of("tbp").map(str::to_string);
+            // asking for an external bot means we want at least one opponent for it to play
+            let bots = match tbp_command {
+                Some(_) => bots.parse::<usize>().expect("should parse").max(1),
+                None => bots.parse().expect("should parse"),
+            };
+            // somebody has to be at the keyboard
+            let bots = if bots >= players {
+                warn!(
+                    "Only {} of our {} players can be bots",
+                    players - 1,
+                    players
+                );
+                players - 1
+            } else {
+                bots
+            };
+
+            Some(GameType::Double {
+                players,
+                targeting: target.parse::<TargetPolicy>().expect("should parse"),
+                bots,
+                tbp_command,
+            })
+        }
+        ("survival", _) => Some(GameType::Survival),
+        ("coop", _) => Some(GameType::CoOp),
+        ("relay", Some(sub_matches)) => {
+            let pieces = sub_matches.value_of("pieces").unwrap();
+            Some(GameType::Relay(pieces.parse().expect("should parse")))
+        }
+        ("swap", Some(sub_matches)) => {
+            let trigger = match sub_matches.value_of("locks") {
+                Some(locks) => SwapTrigger::Locks(locks.parse().expect("should parse")),
+                None => {
+                    let seconds = sub_matches.value_of("seconds").unwrap_or("30");
+                    SwapTrigger::Seconds(seconds.parse().expect("should parse"))
+                }
+            };
+            Some(GameType::Swap(trigger))
+        }
+        // no game means we start at the menu
+        _ => None,
     };
 
-    let mut game_data = GameDataBuilder::default()
+    let subcommand_matches = matches.subcommand().1.unwrap_or(&matches);
+    let best_of = subcommand_matches.value_of("best-of").unwrap();
+    let margin_delay = subcommand_matches.value_of("margin-delay").unwrap();
+    let garbage_delay = subcommand_matches.value_of("garbage-delay").unwrap();
+    let match_config = MatchConfig {
+        best_of: best_of.parse().expect("should parse"),
+        margin_time: MarginTime {
+            delay: margin_delay.parse().expect("should parse"),
+            ..MarginTime::default()
+        },
+        garbage_timing: GarbageTiming {
+            entry_delay: garbage_delay.parse().expect("should parse"),
+            ..GarbageTiming::default()
+        },
+    };
+
+    let mut hints = Hints::open(hints_path);
+    if subcommand_matches.is_present("hints") {
+        hints.enabled = true;
+    }
+
+    let game_data = GameDataBuilder::default()
         // Manages input events
         .with_bundle(InputBundle::<GameInput>::new().with_bindings_from_file(&key_bindings_path)?)?
         .with_bundle(TransformBundle::new())?
         // .with_known_desc(HideHierarchySystemDesc::default())
         // Manages the various Resources for a UI
-        // .with_bundle(UiBundle::<GameInput>::new())?
+        .with_bundle(UiBundle::<GameInput>::new())?
         // This bundle handles the rendering
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
@@ -77,16 +268,48 @@ fn main() -> amethyst::Result<()> {
                     RenderToWindow::from_config_path(display_config_path)?
                         .with_clear([1., 1., 1., 1.0]),
                 )
-                .with_plugin(RenderFlat2D::default()),
+                .with_plugin(RenderFlat2D::default())
+                .with_plugin(RenderUi::default()),
         )?
         // Our own systems
         .with_known_desc(SpriteLoaderDesc::default());
 
-    game_data = game_type.setup(game_data)?;
-
-    let mut game = Application::build(assets_dir, GameState)?
+    let mut game = Application::build(assets_dir, MenuState::new(game_type))?
+        .with_resource(match_config)
+        .with_resource(hints)
+        .with_resource(InputDisplay {
+            enabled: subcommand_matches.is_present("input-display"),
+            ..InputDisplay::default()
+        })
+        .with_resource(HandlingSettings::open(handling_path))
         .with_frame_limit(FrameRateLimitStrategy::Unlimited, 60)
         .build(game_data)?;
     game.run();
     Ok(())
 }
+
+/// let the CPU play our side of a network game
+fn bot_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("bot")
+        .long("bot")
+        .help("Let the CPU play for us, so whoever connects has someone to play against")
+}
+
+/// a swap every zero seconds or locks would hand our boards back and forth every frame
+fn above_zero<T: FromStr + PartialOrd + Default>(value: String) -> Result<(), String> {
+    match value.parse::<T>() {
+        Ok(parsed) if parsed > T::default() => Ok(()),
+        _ => Err(format!("{} should be a number above zero", value)),
+    }
+}
+
+/// a battle needs two of us, and we only have room and keys for so many
+fn player_count(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(players) if (2..=MAX_LOCAL_PLAYERS).contains(&players) => Ok(()),
+        _ => Err(format!(
+            "{} should be a number from 2 to {}",
+            value, MAX_LOCAL_PLAYERS
+        )),
+    }
+}
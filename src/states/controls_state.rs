@@ -0,0 +1,307 @@
+use amethyst::config::Config;
+use amethyst::ecs::Entity;
+use amethyst::input::{get_key, is_close_requested, Button, InputHandler, VirtualKeyCode};
+use amethyst::prelude::*;
+use amethyst::ui::{Anchor, UiText};
+use amethyst::utils::application_root_dir;
+use amethyst::winit::{ElementState, Event, WindowEvent};
+use log::warn;
+
+use crate::events::UserInput;
+use crate::input::{GameActions, GameInput};
+use crate::states::delete_entities;
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::MAX_LOCAL_PLAYERS;
+
+const LINE_HEIGHT: f32 = 40.;
+
+const PLAYER_KEYS: [VirtualKeyCode; MAX_LOCAL_PLAYERS] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+];
+
+const INPUTS: [UserInput; 6] = [
+    UserInput::Left,
+    UserInput::Right,
+    UserInput::RotateClockwise,
+    UserInput::DropSoft,
+    UserInput::DropHard,
+    UserInput::Hold,
+];
+
+/// Rebind each player's keys, our bindings change straight away and are saved when we leave
+#[derive(Default)]
+pub struct ControlsState {
+    player: usize,
+    selected: usize,
+    /// the next key pressed goes to our selected action
+    waiting: bool,
+    /// whether we have bindings to save
+    changed: bool,
+    message: String,
+    title: Option<Entity>,
+    lines: Vec<Entity>,
+    status: Option<Entity>,
+    entities: Vec<Entity>,
+}
+
+impl ControlsState {
+    fn action(&self) -> GameActions {
+        GameActions::Player(self.player, INPUTS[self.selected])
+    }
+
+    fn refresh(&self, world: &World) {
+        let input_handler = world.read_resource::<InputHandler<GameInput>>();
+        let mut ui_text = world.write_storage::<UiText>();
+
+        if let Some(text) = self.title.and_then(|title| ui_text.get_mut(title)) {
+            text.text = format!("Player {} controls", self.player + 1);
+        }
+
+        for (i, (line, &input)) in self.lines.iter().zip(&INPUTS).enumerate() {
+            if let Some(text) = ui_text.get_mut(*line) {
+                let marker = match (i == self.selected, self.waiting) {
+                    (true, true) => "? ",
+                    (true, false) => "> ",
+                    (false, _) => "",
+                };
+                let keys = bound_keys(&input_handler, &GameActions::Player(self.player, input));
+                text.text = format!("{}{:?}  {}", marker, input, keys.join(", "));
+            }
+        }
+
+        if let Some(text) = self.status.and_then(|status| ui_text.get_mut(status)) {
+            text.text = self.message.clone();
+        }
+    }
+
+    /// give our selected action this key and nothing else on the keyboard, returning whether it took
+    fn bind(&mut self, world: &World, key: VirtualKeyCode, scancode: u32) -> bool {
+        let action = self.action();
+        let mut input_handler = world.write_resource::<InputHandler<GameInput>>();
+
+        let taken = input_handler
+            .bindings
+            .actions()
+            .filter(|&&other| other != action)
+            .find(|other| {
+                input_handler.bindings.action_bindings(*other).any(|combo| {
+                    combo.contains(&Button::Key(key)) || combo.contains(&Button::ScanCode(scancode))
+                })
+            })
+            .copied();
+
+        if let Some(other) = taken {
+            self.message = format!(
+                "{:?} is already {}, pick another key",
+                key,
+                describe(&other)
+            );
+            return false;
+        }
+
+        // controller buttons stay where they are, only our keys are replaced
+        let keys: Vec<Vec<Button>> = input_handler
+            .bindings
+            .action_bindings(&action)
+            .filter(|combo| combo.iter().all(is_key))
+            .map(|combo| combo.to_vec())
+            .collect();
+        for combo in keys {
+            input_handler
+                .bindings
+                .remove_action_binding(&action, &combo);
+        }
+
+        match input_handler
+            .bindings
+            .insert_action_binding(action, vec![Button::Key(key)])
+        {
+            Ok(()) => {
+                self.message = format!("{} is now {:?}", describe(&action), key);
+                self.changed = true;
+                true
+            }
+            Err(e) => {
+                self.message = format!("Couldn't bind {:?}: {}", key, e);
+                false
+            }
+        }
+    }
+
+    fn save(&self, world: &World) {
+        if !self.changed {
+            return;
+        }
+
+        let path = match application_root_dir() {
+            Ok(root) => root.join("resources").join("config").join("input.ron"),
+            Err(e) => {
+                warn!("Couldn't find where to save our controls: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = world
+            .read_resource::<InputHandler<GameInput>>()
+            .bindings
+            .write(&path)
+        {
+            warn!("Couldn't save our controls to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// the keyboard keys bound to an action on their own
+fn bound_keys(input_handler: &InputHandler<GameInput>, action: &GameActions) -> Vec<String> {
+    input_handler
+        .bindings
+        .action_bindings(action)
+        .filter_map(|combo| match combo {
+            [Button::Key(key)] => Some(format!("{:?}", key)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the scancode of a keyboard event, since input.ron can bind keys by either
+fn get_scancode(event: &Event) -> Option<u32> {
+    match event {
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { input, .. },
+            ..
+        } => Some(input.scancode),
+        _ => None,
+    }
+}
+
+fn is_key(button: &Button) -> bool {
+    match button {
+        Button::Key(_) | Button::ScanCode(_) => true,
+        _ => false,
+    }
+}
+
+fn describe(action: &GameActions) -> String {
+    match action {
+        GameActions::Player(player, input) => format!("player {}'s {:?}", player + 1, input),
+        GameActions::Solo(input) => format!("player 1's {:?} when playing alone", input),
+        GameActions::Debug => "debug".to_string(),
+    }
+}
+
+impl SimpleState for ControlsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let title = UiTextConfig {
+            id: "controls_title",
+            y: -LINE_HEIGHT * 2.,
+            height: LINE_HEIGHT * 2.,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build("", world);
+        self.title = Some(title);
+        self.entities.push(title);
+
+        for i in 0..INPUTS.len() {
+            let line = UiTextConfig {
+                id: "controls_item",
+                y: -LINE_HEIGHT * (i as f32 + 5.),
+                height: LINE_HEIGHT,
+                anchor: Anchor::TopMiddle,
+                ..UiTextConfig::default()
+            }
+            .build("", world);
+            self.lines.push(line);
+            self.entities.push(line);
+        }
+
+        let status = UiTextConfig {
+            id: "controls_status",
+            y: -LINE_HEIGHT * (INPUTS.len() as f32 + 6.),
+            width: 1200.,
+            height: LINE_HEIGHT,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build("", world);
+        self.status = Some(status);
+        self.entities.push(status);
+
+        let help = UiTextConfig {
+            id: "controls_help",
+            y: -LINE_HEIGHT * (INPUTS.len() as f32 + 7.),
+            width: 1200.,
+            height: LINE_HEIGHT / 2.,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build(
+            "1-4 player, Up/Down action, Enter to press new keys, Esc to stop or save",
+            world,
+        );
+        self.entities.push(help);
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.save(data.world);
+
+        self.title = None;
+        self.status = None;
+        self.lines.clear();
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            let (key, scancode) = match (get_key(&event), get_scancode(&event)) {
+                (Some((key, ElementState::Pressed)), Some(scancode)) => (key, scancode),
+                _ => return Trans::None,
+            };
+
+            if self.waiting {
+                if key == VirtualKeyCode::Escape {
+                    self.waiting = false;
+                    self.message.clear();
+                } else if self.bind(data.world, key, scancode) {
+                    // walk through the rest of our actions so a whole player is quick to set up
+                    self.selected += 1;
+                    if self.selected == INPUTS.len() {
+                        self.selected = 0;
+                        self.waiting = false;
+                    }
+                }
+            } else if key == VirtualKeyCode::Escape {
+                return Trans::Pop;
+            } else if let Some(player) = PLAYER_KEYS.iter().position(|&k| k == key) {
+                self.player = player;
+            } else if key == VirtualKeyCode::Up {
+                self.selected = (self.selected + INPUTS.len() - 1) % INPUTS.len();
+            } else if key == VirtualKeyCode::Down {
+                self.selected = (self.selected + 1) % INPUTS.len();
+            } else if key == VirtualKeyCode::Return {
+                self.waiting = true;
+                self.message = "Press a key, Esc to stop".to_string();
+            } else {
+                return Trans::None;
+            }
+
+            self.refresh(data.world);
+        }
+        Trans::None
+    }
+}
@@ -0,0 +1,98 @@
+use amethyst::core::Time;
+use amethyst::ecs::Entity;
+use amethyst::input::is_close_requested;
+use amethyst::prelude::*;
+use amethyst::ui::UiText;
+
+use crate::states::delete_entities;
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::NetworkStart;
+
+const COUNTDOWN_SECONDS: f32 = 3.;
+
+/// A 3-2-1 on top of our freshly built game before anything moves
+pub struct CountdownState {
+    /// seconds until we start, networked games wait for their start to be scheduled
+    timer: Option<f32>,
+    entities: Vec<Entity>,
+}
+
+impl Default for CountdownState {
+    fn default() -> Self {
+        CountdownState {
+            timer: Some(COUNTDOWN_SECONDS),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl CountdownState {
+    fn text(&self) -> String {
+        match self.timer {
+            // our server may count a little longer to cover our client's latency
+            Some(timer) => format!("{}", timer.ceil().max(1.).min(COUNTDOWN_SECONDS)),
+            None => "Waiting".to_string(),
+        }
+    }
+}
+
+impl SimpleState for CountdownState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if data.world.has_value::<NetworkStart>() {
+            self.timer = None;
+        }
+
+        let text = UiTextConfig {
+            id: "countdown",
+            width: 1200.,
+            height: 200.,
+            ..UiTextConfig::default()
+        }
+        .build(&self.text(), data.world);
+
+        self.entities.push(text);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn handle_event(
+        &mut self,
+        _data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if self.timer.is_none() {
+            self.timer = data
+                .world
+                .try_fetch_mut::<NetworkStart>()
+                .and_then(|mut start| start.take_countdown());
+        }
+
+        if let Some(timer) = &mut self.timer {
+            *timer -= data.world.read_resource::<Time>().delta_seconds();
+            if *timer <= 0. {
+                return Trans::Pop;
+            }
+        }
+
+        let text = self.text();
+        let mut ui_text = data.world.write_storage::<UiText>();
+        for entity in &self.entities {
+            if let Some(ui_text) = ui_text.get_mut(*entity) {
+                ui_text.text = text.clone();
+            }
+        }
+
+        Trans::None
+    }
+}
@@ -0,0 +1,161 @@
+use amethyst::ecs::Entity;
+use amethyst::input::{is_close_requested, is_key_down, VirtualKeyCode};
+use amethyst::prelude::*;
+use amethyst::ui::Anchor;
+use log::info;
+
+use crate::states::{delete_entities, ControlsState, PlayingState, SettingsState};
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::{GameType, SwapTrigger, TargetPolicy};
+
+const LINE_HEIGHT: f32 = 40.;
+
+/// Pick a game to play, our other states sit on top of this one
+pub struct MenuState {
+    /// the game we were asked to start from the command line
+    initial: Option<GameType>,
+    entities: Vec<Entity>,
+}
+
+impl MenuState {
+    pub fn new(initial: Option<GameType>) -> Self {
+        MenuState {
+            initial,
+            entities: Vec::new(),
+        }
+    }
+
+    fn show(&mut self, world: &mut World) {
+        let title = UiTextConfig {
+            id: "menu_title",
+            y: -LINE_HEIGHT * 2.,
+            height: LINE_HEIGHT * 2.,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build("Tetris for Two", world);
+        self.entities.push(title);
+
+        let lines = menu_items()
+            .into_iter()
+            .map(|(_, name, _)| name)
+            .chain(vec!["S  Settings", "C  Controls", "Esc  Quit"]);
+
+        for (i, name) in lines.enumerate() {
+            let line = UiTextConfig {
+                id: "menu_item",
+                y: -LINE_HEIGHT * (i as f32 + 4.),
+                height: LINE_HEIGHT,
+                anchor: Anchor::TopMiddle,
+                ..UiTextConfig::default()
+            }
+            .build(name, world);
+            self.entities.push(line);
+        }
+    }
+}
+
+fn menu_items() -> Vec<(VirtualKeyCode, &'static str, GameType)> {
+    vec![
+        (VirtualKeyCode::Key1, "1  Single", GameType::Single),
+        (
+            VirtualKeyCode::Key2,
+            "2  Versus",
+            GameType::Double {
+                players: 2,
+                targeting: TargetPolicy::Rotating,
+                bots: 0,
+                tbp_command: None,
+            },
+        ),
+        (
+            VirtualKeyCode::Key3,
+            "3  Battle",
+            GameType::Double {
+                players: 4,
+                targeting: TargetPolicy::Rotating,
+                bots: 0,
+                tbp_command: None,
+            },
+        ),
+        (VirtualKeyCode::Key4, "4  Survival", GameType::Survival),
+        (VirtualKeyCode::Key5, "5  Co-op", GameType::CoOp),
+        (VirtualKeyCode::Key6, "6  Relay", GameType::Relay(1)),
+        (
+            VirtualKeyCode::Key7,
+            "7  Swap",
+            GameType::Swap(SwapTrigger::Seconds(30.)),
+        ),
+        (
+            VirtualKeyCode::Key8,
+            "8  Versus CPU",
+            GameType::Double {
+                players: 2,
+                targeting: TargetPolicy::Rotating,
+                bots: 1,
+                tbp_command: None,
+            },
+        ),
+        (
+            VirtualKeyCode::Key9,
+            "9  Finesse Trainer",
+            GameType::Trainer,
+        ),
+    ]
+}
+
+impl SimpleState for MenuState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        info!("starting");
+
+        self.show(data.world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn on_pause(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.show(data.world);
+    }
+
+    fn handle_event(
+        &mut self,
+        _data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            // Check if the window should be closed
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::S) {
+                return Trans::Push(Box::new(SettingsState::default()));
+            }
+
+            if is_key_down(&event, VirtualKeyCode::C) {
+                return Trans::Push(Box::new(ControlsState::default()));
+            }
+
+            for (key, _, game_type) in menu_items() {
+                if is_key_down(&event, key) {
+                    return Trans::Push(Box::new(PlayingState::new(game_type)));
+                }
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, _data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // skip straight to a game if we were given one
+        match self.initial.take() {
+            Some(game_type) => Trans::Push(Box::new(PlayingState::new(game_type))),
+            None => Trans::None,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use amethyst::core::Transform;
+use amethyst::ecs::{Entity, Join};
+use amethyst::prelude::*;
+use amethyst::renderer::Camera;
+use amethyst::window::ScreenDimensions;
+
+pub use controls_state::ControlsState;
+pub use countdown_state::CountdownState;
+pub use menu_state::MenuState;
+pub use paused_state::PausedState;
+pub use playing_state::PlayingState;
+pub use results_state::ResultsState;
+pub use settings_state::SettingsState;
+
+mod controls_state;
+mod countdown_state;
+mod menu_state;
+mod paused_state;
+mod playing_state;
+mod results_state;
+mod settings_state;
+
+/// The area our game is laid out in, the camera zooms out if it doesn't fit on screen
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PlayArea {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Filled in by our control systems once a game is finished, so we know to show the results
+#[derive(Clone, Debug, Default)]
+pub struct GameOver {
+    pub summary: Option<String>,
+    /// lines listed under our summary, like our leaderboard
+    pub details: Vec<String>,
+}
+
+/// how many world units our camera fits in each pixel on screen
+pub(crate) fn camera_scale(world: &World) -> f32 {
+    let play_area = world
+        .try_fetch::<PlayArea>()
+        .map(|play_area| *play_area)
+        .unwrap_or_default();
+
+    scale_to_fit(&world.read_resource::<ScreenDimensions>(), play_area)
+}
+
+/// keep our aspect ratio but zoom out until everything fits
+pub(crate) fn scale_to_fit(dimensions: &ScreenDimensions, play_area: PlayArea) -> f32 {
+    1_f32
+        .max(play_area.width / dimensions.width())
+        .max(play_area.height / dimensions.height())
+}
+
+fn init_camera(world: &mut World) -> Entity {
+    let dimensions = (*world.read_resource::<ScreenDimensions>()).clone();
+
+    let scale = camera_scale(world);
+    let width = dimensions.width() * scale;
+    let height = dimensions.height() * scale;
+
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(width * 0.5, height * 0.5, 1.);
+
+    world
+        .create_entity()
+        .with(Camera::standard_2d(width, height))
+        .with(transform)
+        .build()
+}
+
+fn live_entities(world: &World) -> HashSet<Entity> {
+    (&*world.entities()).join().collect()
+}
+
+/// delete everything that was created since `before` was taken
+fn delete_entities_since(world: &mut World, before: &HashSet<Entity>) {
+    let created: Vec<Entity> = live_entities(world).difference(before).copied().collect();
+
+    world
+        .delete_entities(&created)
+        .expect("We only delete entities that are alive");
+}
+
+fn delete_entities(world: &mut World, entities: &mut Vec<Entity>) {
+    world
+        .delete_entities(entities)
+        .expect("We only delete entities that are alive");
+
+    entities.clear();
+}
@@ -0,0 +1,127 @@
+use amethyst::core::{Hidden, Time};
+use amethyst::ecs::Entity;
+use amethyst::input::{is_close_requested, is_key_down, VirtualKeyCode};
+use amethyst::prelude::*;
+
+use crate::states::delete_entities;
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::NetworkPause;
+
+// our socket closes with our game, so give our leaving a moment to go out first
+const LEAVE_SECONDS: f32 = 0.25;
+// and don't wait forever on an opponent who's already gone
+const LEAVE_TIMEOUT_SECONDS: f32 = 1.;
+
+/// Sits on top of our game so its systems stop running until we resume
+pub struct PausedState {
+    /// our game's entities, hidden so nobody can plan ahead while we're paused
+    hidden: Vec<Entity>,
+    /// seconds since we asked to leave a networked game
+    leaving: Option<f32>,
+    entities: Vec<Entity>,
+}
+
+impl PausedState {
+    pub fn new(hidden: Vec<Entity>) -> Self {
+        PausedState {
+            hidden,
+            leaving: None,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl SimpleState for PausedState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        {
+            let mut hidden_storage = data.world.write_storage::<Hidden>();
+            for entity in &self.hidden {
+                hidden_storage
+                    .insert(*entity, Hidden)
+                    .expect("Our game's entities should still be alive");
+            }
+        }
+
+        let text = UiTextConfig {
+            id: "paused",
+            width: 1200.,
+            ..UiTextConfig::default()
+        }
+        .build("Paused  Esc to resume, Q for the menu", data.world);
+
+        self.entities.push(text);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        delete_entities(data.world, &mut self.entities);
+
+        let mut hidden_storage = data.world.write_storage::<Hidden>();
+        for entity in &self.hidden {
+            hidden_storage.remove(*entity);
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                // over the network we wait until our opponent agrees before resuming
+                return match data.world.try_fetch_mut::<NetworkPause>() {
+                    Some(mut pause) => {
+                        pause.request(false);
+                        Trans::None
+                    }
+                    None => Trans::Pop,
+                };
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Q) {
+                // over the network we let our opponent know before we go
+                return match data.world.try_fetch_mut::<NetworkPause>() {
+                    Some(mut pause) => {
+                        pause.leave();
+                        self.leaving.get_or_insert(0.);
+                        Trans::None
+                    }
+                    None => quit(),
+                };
+            }
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let (stopped, left) = data
+            .world
+            .try_fetch::<NetworkPause>()
+            .map_or((true, false), |pause| (pause.stopped(), pause.left()));
+
+        if let Some(waited) = &mut self.leaving {
+            *waited += data.world.read_resource::<Time>().delta_seconds();
+
+            return if (left && *waited >= LEAVE_SECONDS) || *waited >= LEAVE_TIMEOUT_SECONDS {
+                quit()
+            } else {
+                Trans::None
+            };
+        }
+
+        if stopped {
+            Trans::None
+        } else {
+            Trans::Pop
+        }
+    }
+}
+
+/// drop ourselves and our game to get back to the menu
+fn quit() -> SimpleTrans {
+    Trans::Sequence(vec![Trans::Pop, Trans::Pop])
+}
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use amethyst::ecs::Entity;
+use amethyst::input::{is_close_requested, is_key_down, VirtualKeyCode};
+use amethyst::prelude::*;
+use log::{error, info};
+
+use crate::states::{
+    delete_entities_since, init_camera, live_entities, CountdownState, GameOver, PausedState,
+    PlayArea, ResultsState,
+};
+use crate::systems::utils::{StateSystems, SystemsBuilder};
+use crate::systems::{GameType, NetworkPause};
+
+/// Runs the systems for one game, they're built when we start and torn down when we stop
+pub struct PlayingState {
+    game_type: GameType,
+    systems: Option<StateSystems>,
+    /// everything that existed before our game, the rest belongs to us
+    entities_before: HashSet<Entity>,
+    /// ours too, but it has to keep showing our pause screen
+    camera: Option<Entity>,
+    counted_down: bool,
+}
+
+impl PlayingState {
+    pub fn new(game_type: GameType) -> Self {
+        PlayingState {
+            game_type,
+            systems: None,
+            entities_before: HashSet::new(),
+            camera: None,
+            counted_down: false,
+        }
+    }
+
+    fn pause(&self, world: &World) -> SimpleTrans {
+        let entities = live_entities(world)
+            .difference(&self.entities_before)
+            .filter(|&&entity| Some(entity) != self.camera)
+            .copied()
+            .collect();
+
+        Trans::Push(Box::new(PausedState::new(entities)))
+    }
+}
+
+impl SimpleState for PlayingState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        info!("starting {:?}", self.game_type);
+
+        let StateData { world, .. } = data;
+
+        world.insert(GameOver::default());
+        self.entities_before = live_entities(world);
+
+        match self.game_type.clone().setup(SystemsBuilder::new(world)) {
+            Ok(systems) => self.systems = Some(systems.build()),
+            Err(e) => error!("Couldn't start {:?}: {}", self.game_type, e),
+        }
+
+        self.camera = Some(init_camera(world));
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+
+        if let Some(systems) = self.systems.take() {
+            systems.foreground.dispose(world);
+            systems.background.dispose(world);
+        }
+
+        delete_entities_since(world, &self.entities_before);
+        world.remove::<PlayArea>();
+        self.game_type.teardown(world);
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                // over the network we stop straight away, our opponent stops once they hear from us
+                if let Some(mut pause) = data.world.try_fetch_mut::<NetworkPause>() {
+                    pause.request(true);
+                }
+
+                return self.pause(data.world);
+            }
+        }
+        Trans::None
+    }
+
+    fn shadow_update(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if let Some(systems) = &mut self.systems {
+            systems.background.dispatch(data.world);
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let systems = match &mut self.systems {
+            Some(systems) => systems,
+            // we couldn't build our game so head back to the menu
+            None => return Trans::Pop,
+        };
+
+        if !self.counted_down {
+            self.counted_down = true;
+
+            return Trans::Push(Box::new(CountdownState::default()));
+        }
+
+        let network_paused = data
+            .world
+            .try_fetch::<NetworkPause>()
+            .map_or(false, |pause| pause.stopped());
+        if network_paused {
+            return self.pause(data.world);
+        }
+
+        systems.foreground.dispatch(data.world);
+
+        let mut game_over = data.world.write_resource::<GameOver>();
+        match game_over.summary.take() {
+            Some(summary) => Trans::Switch(Box::new(ResultsState::new(
+                self.game_type.clone(),
+                summary,
+                game_over.details.split_off(0),
+            ))),
+            None => Trans::None,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use amethyst::ecs::Entity;
+use amethyst::input::{is_close_requested, is_key_down, VirtualKeyCode};
+use amethyst::prelude::*;
+
+use crate::states::{delete_entities, PlayingState};
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::GameType;
+
+const DETAIL_HEIGHT: f32 = 30.;
+
+/// Shows how our last game went and offers a rematch
+pub struct ResultsState {
+    game_type: GameType,
+    summary: String,
+    details: Vec<String>,
+    entities: Vec<Entity>,
+}
+
+impl ResultsState {
+    pub fn new(game_type: GameType, summary: String, details: Vec<String>) -> Self {
+        ResultsState {
+            game_type,
+            summary,
+            details,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl SimpleState for ResultsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        // keep everything centered however many details we list
+        let details_height = self.details.len() as f32 * DETAIL_HEIGHT;
+        let top = 50. + details_height / 2.;
+
+        let summary = UiTextConfig {
+            id: "results_summary",
+            y: top,
+            width: 1200.,
+            ..UiTextConfig::default()
+        }
+        .build(&self.summary, data.world);
+        self.entities.push(summary);
+
+        for (i, detail) in self.details.iter().enumerate() {
+            let detail = UiTextConfig {
+                id: "results_detail",
+                y: top - 50. - DETAIL_HEIGHT * i as f32,
+                width: 1200.,
+                height: DETAIL_HEIGHT,
+                ..UiTextConfig::default()
+            }
+            .build(detail, data.world);
+            self.entities.push(detail);
+        }
+
+        let options = UiTextConfig {
+            id: "results_options",
+            y: top - 100. - details_height,
+            width: 1200.,
+            ..UiTextConfig::default()
+        }
+        .build("Enter for a rematch, Esc for the menu", data.world);
+        self.entities.push(options);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn handle_event(
+        &mut self,
+        _data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Pop;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Return) {
+                return Trans::Switch(Box::new(PlayingState::new(self.game_type.clone())));
+            }
+        }
+        Trans::None
+    }
+}
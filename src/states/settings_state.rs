@@ -0,0 +1,206 @@
+use amethyst::ecs::Entity;
+use amethyst::input::{is_close_requested, is_key_down, VirtualKeyCode};
+use amethyst::prelude::*;
+use amethyst::ui::{Anchor, UiText};
+use amethyst::utils::application_root_dir;
+use log::warn;
+
+use crate::input::{Handling, HandlingSettings, OpposingKeys};
+use crate::states::delete_entities;
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::MAX_LOCAL_PLAYERS;
+
+const LINE_HEIGHT: f32 = 40.;
+
+const PLAYER_KEYS: [VirtualKeyCode; MAX_LOCAL_PLAYERS] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+];
+
+/// The parts of a player's handling we can change from here
+#[derive(Copy, Clone, Debug)]
+enum Setting {
+    Das,
+    Arr,
+    SoftDropFactor,
+    DasCutDelay,
+    OpposingKeys,
+}
+
+const SETTINGS: [Setting; 5] = [
+    Setting::Das,
+    Setting::Arr,
+    Setting::SoftDropFactor,
+    Setting::DasCutDelay,
+    Setting::OpposingKeys,
+];
+
+impl Setting {
+    /// move our setting along by some presses, each one a step between the lowest and highest we go
+    fn adjust(self, handling: &mut Handling, steps: f32) {
+        let (value, step, min, max) = match self {
+            Setting::Das => (&mut handling.das, 0.01, 0., 1.),
+            Setting::Arr => (&mut handling.arr, 0.01, 0., 0.5),
+            Setting::SoftDropFactor => (&mut handling.soft_drop_factor, 1., 1., 100.),
+            Setting::DasCutDelay => (&mut handling.das_cut_delay, 0.01, 0., 0.5),
+            Setting::OpposingKeys => {
+                handling.opposing_keys = handling.opposing_keys.other();
+                return;
+            }
+        };
+
+        // stay on whole steps so our file doesn't fill up with rounding errors
+        *value = (((*value / step).round() + steps) * step).max(min).min(max);
+    }
+
+    fn label(self, handling: &Handling) -> String {
+        match self {
+            Setting::Das => format!("DAS  {:.2}s", handling.das),
+            Setting::Arr if handling.arr <= 0. => "ARR  instant".to_string(),
+            Setting::Arr => format!("ARR  {:.2}s", handling.arr),
+            Setting::SoftDropFactor => format!("Soft drop  {}x", handling.soft_drop_factor),
+            Setting::DasCutDelay => format!("DAS cut delay  {:.2}s", handling.das_cut_delay),
+            Setting::OpposingKeys => match handling.opposing_keys {
+                OpposingKeys::LastPressed => "Left and right  last pressed wins".to_string(),
+                OpposingKeys::Cancel => "Left and right  cancel out".to_string(),
+            },
+        }
+    }
+}
+
+/// Change how each player's held keys repeat, saved when we leave
+#[derive(Default)]
+pub struct SettingsState {
+    player: usize,
+    selected: usize,
+    title: Option<Entity>,
+    lines: Vec<Entity>,
+    entities: Vec<Entity>,
+}
+
+impl SettingsState {
+    fn refresh(&self, world: &World) {
+        let handling = world
+            .read_resource::<HandlingSettings>()
+            .player(self.player);
+        let mut ui_text = world.write_storage::<UiText>();
+
+        if let Some(text) = self.title.and_then(|title| ui_text.get_mut(title)) {
+            text.text = format!("Player {} handling", self.player + 1);
+        }
+
+        for (i, (line, setting)) in self.lines.iter().zip(&SETTINGS).enumerate() {
+            if let Some(text) = ui_text.get_mut(*line) {
+                let marker = if i == self.selected { "> " } else { "" };
+                text.text = format!("{}{}", marker, setting.label(&handling));
+            }
+        }
+    }
+
+    fn save(&self, world: &World) {
+        match application_root_dir() {
+            Ok(root) => world
+                .read_resource::<HandlingSettings>()
+                .save(root.join("resources").join("config").join("handling.ron")),
+            Err(e) => warn!("Couldn't find where to save our handling: {}", e),
+        }
+    }
+}
+
+impl SimpleState for SettingsState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        world
+            .entry::<HandlingSettings>()
+            .or_insert_with(HandlingSettings::default);
+
+        let title = UiTextConfig {
+            id: "settings_title",
+            y: -LINE_HEIGHT * 2.,
+            height: LINE_HEIGHT * 2.,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build("", world);
+        self.title = Some(title);
+        self.entities.push(title);
+
+        for i in 0..SETTINGS.len() {
+            let line = UiTextConfig {
+                id: "settings_item",
+                y: -LINE_HEIGHT * (i as f32 + 5.),
+                height: LINE_HEIGHT,
+                anchor: Anchor::TopMiddle,
+                ..UiTextConfig::default()
+            }
+            .build("", world);
+            self.lines.push(line);
+            self.entities.push(line);
+        }
+
+        let help = UiTextConfig {
+            id: "settings_help",
+            y: -LINE_HEIGHT * (SETTINGS.len() as f32 + 6.),
+            width: 1200.,
+            height: LINE_HEIGHT / 2.,
+            anchor: Anchor::TopMiddle,
+            ..UiTextConfig::default()
+        }
+        .build(
+            "1-4 player, Up/Down setting, Left/Right change, Esc to save",
+            world,
+        );
+        self.entities.push(help);
+
+        self.refresh(world);
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        self.save(data.world);
+
+        self.title = None;
+        self.lines.clear();
+        delete_entities(data.world, &mut self.entities);
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) {
+                return Trans::Quit;
+            }
+
+            if is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Pop;
+            }
+
+            if let Some(player) = PLAYER_KEYS.iter().position(|&key| is_key_down(&event, key)) {
+                self.player = player;
+            } else if is_key_down(&event, VirtualKeyCode::Up) {
+                self.selected = (self.selected + SETTINGS.len() - 1) % SETTINGS.len();
+            } else if is_key_down(&event, VirtualKeyCode::Down) {
+                self.selected = (self.selected + 1) % SETTINGS.len();
+            } else if is_key_down(&event, VirtualKeyCode::Left)
+                || is_key_down(&event, VirtualKeyCode::Right)
+            {
+                let steps = if is_key_down(&event, VirtualKeyCode::Left) {
+                    -1.
+                } else {
+                    1.
+                };
+                let mut settings = data.world.write_resource::<HandlingSettings>();
+                SETTINGS[self.selected].adjust(settings.player_mut(self.player), steps);
+            } else {
+                return Trans::None;
+            }
+
+            self.refresh(data.world);
+        }
+        Trans::None
+    }
+}
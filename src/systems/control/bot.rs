@@ -0,0 +1,449 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+
+use amethyst::config::Config;
+use amethyst::core::Time;
+use amethyst::error::Error as AmethystError;
+use amethyst::utils::application_root_dir;
+use crossbeam::channel::Sender;
+use log::warn;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{TimedInput, UserInput};
+use crate::systems::tetris::{Board, BoardPixel, Piece, PieceState, Placement, Tetrimino};
+use crate::ExpectSender;
+
+// how much our evaluator cares about each part of the board after a placement
+const LINE_WEIGHT: f32 = 0.76;
+const HEIGHT_WEIGHT: f32 = -0.51;
+const HOLE_WEIGHT: f32 = -0.9;
+const BUMPINESS_WEIGHT: f32 = -0.18;
+const WELL_WEIGHT: f32 = -0.3;
+
+/// How well our bot plays, stored as RON next to our other config
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BotStrength {
+    pub pieces_per_second: f32,
+    /// whether we plan around our next piece too, it's the only one we can see
+    pub look_ahead: bool,
+    /// the chance we drop a piece somewhere random instead of our best spot
+    pub mistake_rate: f32,
+}
+
+impl Default for BotStrength {
+    fn default() -> Self {
+        BotStrength {
+            pieces_per_second: 1.5,
+            look_ahead: true,
+            mistake_rate: 0.05,
+        }
+    }
+}
+
+impl BotStrength {
+    /// load our strength from our config folder
+    pub fn load() -> Result<BotStrength, AmethystError> {
+        Ok(BotStrength::open(
+            application_root_dir()?
+                .join("resources")
+                .join("config")
+                .join("bot.ron"),
+        ))
+    }
+
+    /// load our strength, falling back to the default if the file is missing or unreadable
+    pub fn open<P: AsRef<Path>>(path: P) -> BotStrength {
+        if !path.as_ref().exists() {
+            return BotStrength::default();
+        }
+
+        <BotStrength as Config>::load(path).unwrap_or_else(|e| {
+            warn!("Using the default bot: {}", e);
+            BotStrength::default()
+        })
+    }
+}
+
+/// Plays for a player by sending the same inputs a person would, one piece at a time
+pub trait Bot: Send {
+    /// decide where our new piece goes, anything we were still doing is thrown away
+    fn plan(&mut self, state: &PieceState);
+
+    /// make any inputs that are due
+    fn update(&mut self, time: &Time);
+
+    fn reset(&mut self);
+}
+
+/// Inputs spread out over the time we have for a piece, so we don't play faster than we should
+#[derive(Default)]
+pub(super) struct PlannedInputs {
+    inputs: VecDeque<UserInput>,
+    interval: f32,
+    timer: f32,
+}
+
+impl PlannedInputs {
+    pub fn set(&mut self, inputs: Vec<UserInput>, piece_seconds: f32) {
+        self.interval = piece_seconds / inputs.len().max(1) as f32;
+        self.timer = self.interval;
+        self.inputs = inputs.into();
+    }
+
+    pub fn update(&mut self, time: &Time, input_tx: &Sender<TimedInput>) {
+        if self.inputs.is_empty() {
+            return;
+        }
+
+        // stamp each input with when it was due, like a held key's repeats
+        let frame_end = time.absolute_time();
+        self.timer -= time.delta_seconds();
+        while self.timer <= 0. {
+            match self.inputs.pop_front() {
+                Some(input) => input_tx.send_expect(TimedInput {
+                    input,
+                    at: frame_end
+                        .checked_sub(Duration::from_secs_f32(-self.timer))
+                        .unwrap_or_default(),
+                }),
+                None => break,
+            }
+            self.timer += self.interval;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.inputs.clear();
+        self.timer = 0.;
+    }
+}
+
+/// Our built in bot, it scores every placement it can see
+pub struct CpuBot {
+    strength: BotStrength,
+    input_tx: Sender<TimedInput>,
+    /// the inputs we still need to make for our current piece
+    plan: PlannedInputs,
+}
+
+impl CpuBot {
+    pub fn new(strength: BotStrength, input_tx: Sender<TimedInput>) -> Self {
+        CpuBot {
+            strength,
+            input_tx,
+            plan: PlannedInputs::default(),
+        }
+    }
+
+    fn best_inputs(&self, state: &PieceState) -> Vec<UserInput> {
+        let search = Search {
+            look_ahead: self.strength.look_ahead,
+            ..Search::default()
+        };
+
+        match search.choose(state) {
+            // we'll hear about our new piece once it's out and plan for it then
+            Choice { hold: true, .. } => vec![UserInput::Hold],
+            Choice { placement, .. } => placement
+                .map(|placement| placement.inputs)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Bot for CpuBot {
+    fn plan(&mut self, state: &PieceState) {
+        let mut rng = rand::thread_rng();
+
+        let inputs = if rng.gen::<f32>() < self.strength.mistake_rate {
+            state
+                .board
+                .placements(&state.piece)
+                .choose(&mut rng)
+                .map(|placement| placement.inputs.clone())
+                .unwrap_or_default()
+        } else {
+            self.best_inputs(state)
+        };
+
+        let piece_seconds = 1. / self.strength.pieces_per_second.max(0.1);
+        self.plan.set(inputs, piece_seconds);
+    }
+
+    fn update(&mut self, time: &Time) {
+        self.plan.update(time, &self.input_tx);
+    }
+
+    fn reset(&mut self) {
+        self.plan.clear();
+    }
+}
+
+/// How widely we look when choosing where a piece goes
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Search {
+    /// whether we plan around our next piece too, it's the only one we can see
+    pub look_ahead: bool,
+    /// whether holding is one of our options
+    pub hold: bool,
+    /// whether we consider spots that need tucks or spins to reach
+    pub tucks: bool,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search {
+            look_ahead: true,
+            hold: true,
+            tucks: true,
+        }
+    }
+}
+
+/// What we'd do with a piece
+pub(super) struct Choice {
+    /// hold first, our placement is then for whatever piece comes out
+    pub hold: bool,
+    pub placement: Option<Placement>,
+}
+
+impl Search {
+    pub(super) fn choose(&self, state: &PieceState) -> Choice {
+        let next = [state.next];
+        let queue = if self.look_ahead { &next[..] } else { &[][..] };
+        let spawn_column = spawn_column(&state.piece);
+
+        let best = |piece: &Piece, queue: &[Tetrimino]| {
+            let placements = state
+                .board
+                .placements(piece)
+                .into_iter()
+                .filter(|placement| self.tucks || !placement.inputs.contains(&UserInput::DropSoft))
+                .collect();
+
+            best_of(&state.board, placements, queue, spawn_column)
+        };
+
+        let (placement, score) = best(&state.piece, queue);
+
+        if self.hold && state.can_hold {
+            // holding gives us our held piece, or our next one if we aren't holding anything
+            let (hold_piece, hold_queue) = match state.held {
+                Some(held) => (held, queue),
+                None => (state.next, &[][..]),
+            };
+            let (hold_placement, hold_score) =
+                best(&Piece::spawn(hold_piece, spawn_column), hold_queue);
+
+            if hold_score > score {
+                return Choice {
+                    hold: true,
+                    placement: hold_placement,
+                };
+            }
+        }
+
+        Choice {
+            hold: false,
+            placement,
+        }
+    }
+}
+
+/// our best placement for this piece and how good the board it leaves behind is
+fn best_placement(
+    board: &Board,
+    piece: &Piece,
+    queue: &[Tetrimino],
+    spawn_column: isize,
+) -> (Option<Placement>, f32) {
+    best_of(board, board.placements(piece), queue, spawn_column)
+}
+
+fn best_of(
+    board: &Board,
+    placements: Vec<Placement>,
+    queue: &[Tetrimino],
+    spawn_column: isize,
+) -> (Option<Placement>, f32) {
+    placements
+        .into_iter()
+        .map(|placement| {
+            let (board, lines) = place(board, &placement.piece);
+
+            let score = match queue.split_first() {
+                Some((&next, rest)) => {
+                    let next_piece = Piece::spawn(next, spawn_column);
+                    best_placement(&board, &next_piece, rest, spawn_column).1
+                        + lines as f32 * LINE_WEIGHT
+                }
+                None => evaluate(&board, lines),
+            };
+
+            (Some(placement), score)
+        })
+        .fold((None, f32::MIN), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// the column a freshly spawned piece was spawned at
+fn spawn_column(piece: &Piece) -> isize {
+    match piece.tetrimino {
+        // our O piece spawns one column over from everything else
+        Tetrimino::O => piece.offset.0 - 1,
+        _ => piece.offset.0,
+    }
+}
+
+/// lock our piece into a copy of our board, returning it and how many lines it cleared
+pub(super) fn place(board: &Board, piece: &Piece) -> (Board, usize) {
+    let mut board = board.clone();
+    for (x, y) in piece.board_pixels() {
+        board.set(x, y, BoardPixel::Filled(piece.tetrimino.color()));
+    }
+
+    let mut lines = 0;
+    for y in (0..board.height()).rev() {
+        if board.row_filled(y) {
+            board.clear_row(y);
+            lines += 1;
+        }
+    }
+
+    (board, lines)
+}
+
+/// how much we like a board, higher is better
+fn evaluate(board: &Board, lines: usize) -> f32 {
+    let heights: Vec<usize> = (0..board.width())
+        .map(|x| {
+            (0..board.height())
+                .rev()
+                .find(|&y| board.get(x as isize, y as isize) != BoardPixel::Empty)
+                .map_or(0, |y| y + 1)
+        })
+        .collect();
+
+    let aggregate_height: usize = heights.iter().sum();
+
+    let holes = heights
+        .iter()
+        .enumerate()
+        .map(|(x, &height)| {
+            (0..height)
+                .filter(|&y| board.get(x as isize, y as isize) == BoardPixel::Empty)
+                .count()
+        })
+        .sum::<usize>();
+
+    let bumpiness = heights
+        .windows(2)
+        .map(|pair| (pair[0] as isize - pair[1] as isize).abs() as usize)
+        .sum::<usize>();
+
+    // how far each column sits below both of its neighbours, our walls count as tall neighbours
+    let wells = (0..heights.len())
+        .map(|x| {
+            let left = if x == 0 { usize::MAX } else { heights[x - 1] };
+            let right = heights.get(x + 1).copied().unwrap_or(usize::MAX);
+
+            left.min(right).saturating_sub(heights[x])
+        })
+        .sum::<usize>();
+
+    lines as f32 * LINE_WEIGHT
+        + aggregate_height as f32 * HEIGHT_WEIGHT
+        + holes as f32 * HOLE_WEIGHT
+        + bumpiness as f32 * BUMPINESS_WEIGHT
+        + wells as f32 * WELL_WEIGHT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn board(filled: &[(isize, isize)]) -> Board {
+        let mut board = Board::new();
+        for &(x, y) in filled {
+            board.set(x, y, BoardPixel::Filled(Tetrimino::I.color()));
+        }
+        board
+    }
+
+    #[test]
+    fn empty_board_scores_nothing() {
+        assert_eq!(evaluate(&Board::new(), 0), 0.);
+    }
+
+    #[test]
+    fn each_line_scores_the_same() {
+        let board = board(&[(0, 0), (1, 0)]);
+
+        let one = evaluate(&board, 1) - evaluate(&board, 0);
+        let four = evaluate(&board, 4) - evaluate(&board, 0);
+        assert!((one - LINE_WEIGHT).abs() < EPSILON);
+        assert!((four - 4. * LINE_WEIGHT).abs() < EPSILON);
+    }
+
+    #[test]
+    fn height_costs_us() {
+        let low = board(&[(0, 0), (1, 0)]);
+        let high = board(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        // two more cells, and one more step down to our empty columns
+        let cost = evaluate(&high, 0) - evaluate(&low, 0);
+        assert!((cost - (2. * HEIGHT_WEIGHT + BUMPINESS_WEIGHT)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn holes_cost_us() {
+        // the same heights, but our second board has nothing under its top cell
+        let solid = board(&[(0, 0), (0, 1)]);
+        let hole = board(&[(0, 1)]);
+
+        let cost = evaluate(&hole, 0) - evaluate(&solid, 0);
+        assert!((cost - HOLE_WEIGHT).abs() < EPSILON);
+    }
+
+    #[test]
+    fn bumpy_stacks_cost_us() {
+        let flat = board(&[(0, 0), (1, 0), (2, 0), (3, 0)]);
+        let tower = board(&[(0, 0), (0, 1), (0, 2), (0, 3)]);
+
+        // heights 1 1 1 1 0 against 4 0
+        let cost = evaluate(&tower, 0) - evaluate(&flat, 0);
+        assert!((cost - 3. * BUMPINESS_WEIGHT).abs() < EPSILON);
+    }
+
+    #[test]
+    fn wells_cost_us() {
+        let flat = board(&[(0, 0), (1, 0)]);
+        let well = board(&[(0, 0), (2, 0)]);
+
+        // our gap is one deep and bumps the stack twice more
+        let cost = evaluate(&well, 0) - evaluate(&flat, 0);
+        assert!((cost - (WELL_WEIGHT + 2. * BUMPINESS_WEIGHT)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn walls_count_as_tall() {
+        let width = Board::new().width() as isize;
+        let edge = board(&[(width - 2, 0)]);
+
+        // our last column sits below our wall and the cell next to it
+        let expected = HEIGHT_WEIGHT + 2. * BUMPINESS_WEIGHT + WELL_WEIGHT;
+        assert!((evaluate(&edge, 0) - expected).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,109 @@
+use amethyst::core::ecs::{Read, System, Write};
+use amethyst::core::Time;
+use amethyst::error::Error as AmethystError;
+use crossbeam::channel;
+use log::info;
+
+use crate::systems::control::{LocalPlayer, SinglePlayer, MARGIN};
+use crate::systems::input_display::left_of;
+use crate::systems::input_system::InputSystemDesc;
+use crate::systems::tetris::coop_system::{CoOpGameSystemDesc, RENDERED_COOP_WIDTH};
+use crate::systems::tetris::{PIXEL_DIMENSION, RENDERED_PREVIEW_WIDTH};
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystem, WithKnownSystemDesc};
+use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+
+/// Two players working together on one wide board
+struct CoOpPlayerSystem {
+    started: bool,
+    finished: bool,
+    one: SinglePlayer,
+    two: SinglePlayer,
+}
+
+impl<'s> System<'s> for CoOpPlayerSystem {
+    type SystemData = (Read<'s, Time>, Write<'s, GameOver>);
+
+    fn run(&mut self, (time, mut game_over): Self::SystemData) {
+        if self.finished {
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+
+            self.one.start_game();
+            self.two.start_game();
+        }
+
+        self.one.process_input(&time);
+        self.two.process_input(&time);
+
+        // we share a board so either of us losing means we both lost
+        let one_lost = self.one.handle_events();
+        let two_lost = self.two.handle_events();
+
+        if one_lost || two_lost {
+            self.finished = true;
+
+            info!("Game over!");
+            game_over.summary = Some("Game over!".to_string());
+        }
+    }
+}
+
+pub fn setup<'w>(
+    _: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
+    let (one_input_tx, one_input_rx) = channel::unbounded();
+    let (two_input_tx, two_input_rx) = channel::unbounded();
+
+    let (one_in_tx, one_in_rx) = channel::unbounded();
+    let (one_out_tx, one_out_rx) = channel::unbounded();
+
+    let (two_in_tx, two_in_rx) = channel::unbounded();
+    let (two_out_tx, two_out_rx) = channel::unbounded();
+
+    let position = (MARGIN + RENDERED_PREVIEW_WIDTH + PIXEL_DIMENSION, MARGIN);
+
+    game_data = game_data
+        .with_known_desc(InputSystemDesc {
+            input_txs: vec![one_input_tx, two_input_tx],
+            // our displays go under each player's previews, on their own side of our board
+            overlays: vec![
+                (
+                    position.0 + RENDERED_COOP_WIDTH + PIXEL_DIMENSION,
+                    position.1,
+                ),
+                left_of(position),
+            ],
+        })
+        .with_system_desc(
+            CoOpGameSystemDesc {
+                position,
+                // player two sits on the left side of the keyboard so they get the left side
+                players: vec![(two_in_rx, two_out_tx), (one_in_rx, one_out_tx)],
+            },
+            "game_system_coop",
+            &[],
+        )
+        .with_known(CoOpPlayerSystem {
+            started: false,
+            finished: false,
+            one: SinglePlayer::new(one_input_rx, one_in_tx, one_out_rx),
+            two: SinglePlayer::new(two_input_rx, two_in_tx, two_out_rx),
+        });
+
+    Ok(game_data)
+}
+
+impl KnownSystem<'_> for CoOpPlayerSystem {
+    fn name() -> KnownSystems {
+        KnownSystems::ControlSystem
+    }
+
+    fn dependencies() -> &'static [KnownSystems] {
+        &[]
+    }
+}
@@ -1,104 +1,305 @@
-use amethyst::core::ecs::{Read, System};
+use std::str::FromStr;
+
+use amethyst::core::ecs::{Read, System, Write, WriteStorage};
 use amethyst::core::Time;
+use amethyst::ecs::SystemData;
 use amethyst::error::Error as AmethystError;
-use amethyst::GameDataBuilder;
+use amethyst::prelude::*;
+use amethyst::ui::UiText;
 use crossbeam::channel;
+use rand::seq::SliceRandom;
 
-use crate::systems::control::{LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::systems::control::bot::{BotStrength, CpuBot};
+use crate::systems::control::handicap::Handicaps;
+use crate::systems::control::tbp::TbpBot;
+use crate::systems::control::versus_match::{player_names, VersusMatch};
+use crate::systems::control::{Hints, LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::systems::input_display::beside;
 use crate::systems::input_system::InputSystemDesc;
 use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
-use crate::systems::tetris::RENDERED_WIDTH;
-use crate::systems::utils::{KnownSystem, WithKnownSystem, WithKnownSystemDesc};
+use crate::systems::tetris::{PIXEL_DIMENSION, RENDERED_WIDTH, VISIBLE_HEIGHT};
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystemDesc};
 use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+use crate::PlayArea;
+
+pub const MAX_LOCAL_PLAYERS: usize = 4;
+
+/// our players from left to right on the keyboard, so their boards line up with their hands
+const SCREEN_ORDER: [usize; MAX_LOCAL_PLAYERS] = [1, 2, 0, 3];
+
+/// Who gets the garbage when a player clears lines
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetPolicy {
+    /// a random opponent every attack
+    Random,
+    /// cycle through the opponents one attack at a time
+    Rotating,
+    /// whoever is targeting us, or a random opponent if nobody is
+    Attackers,
+}
+
+impl FromStr for TargetPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(TargetPolicy::Random),
+            "rotating" => Ok(TargetPolicy::Rotating),
+            "attackers" => Ok(TargetPolicy::Attackers),
+            _ => Err(format!("Unknown target policy: {}", s)),
+        }
+    }
+}
 
 struct DoublePlayerSystem {
     started: bool,
-    one: LocalAttackPlayer,
-    two: LocalAttackPlayer,
+    players: Vec<LocalAttackPlayer>,
+    alive: Vec<bool>,
+    targeting: TargetPolicy,
+    /// who each player sent their last attack to
+    targets: Vec<Option<usize>>,
+    versus: VersusMatch,
+}
+
+impl DoublePlayerSystem {
+    fn choose_target(&mut self, attacker: usize) -> Option<usize> {
+        let alive = &self.alive;
+        let opponents: Vec<usize> = (0..self.players.len())
+            .filter(|&i| i != attacker && alive[i])
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let target = match self.targeting {
+            TargetPolicy::Random => opponents.choose(&mut rng).copied(),
+            TargetPolicy::Rotating => {
+                // pick the next opponent after whoever we hit last
+                let last = self.targets[attacker].unwrap_or(attacker);
+                opponents
+                    .iter()
+                    .copied()
+                    .find(|&i| i > last)
+                    .or_else(|| opponents.first().copied())
+            }
+            TargetPolicy::Attackers => {
+                let targets = &self.targets;
+                let attackers: Vec<usize> = opponents
+                    .iter()
+                    .copied()
+                    .filter(|&i| targets[i] == Some(attacker))
+                    .collect();
+
+                attackers
+                    .choose(&mut rng)
+                    .or_else(|| opponents.choose(&mut rng))
+                    .copied()
+            }
+        };
+
+        self.targets[attacker] = target;
+
+        target
+    }
 }
 
-// TODO create an event system for the entire game, we can reuse it for different things
-// start game: send start events
-// won
-// lost
-// etc
 impl<'s> System<'s> for DoublePlayerSystem {
-    type SystemData = Read<'s, Time>;
+    type SystemData = (
+        Read<'s, Time>,
+        WriteStorage<'s, UiText>,
+        Write<'s, GameOver>,
+    );
 
-    fn run(&mut self, time: Self::SystemData) {
+    fn run(&mut self, (time, mut ui_text, mut game_over): Self::SystemData) {
         if !self.started {
             self.started = true;
 
-            self.one.start_game();
-            self.two.start_game();
+            self.players.iter().for_each(|player| player.start_game());
         }
 
-        self.one.process_input(&time);
-        self.two.process_input(&time);
+        let playing = self.versus.playing();
+        let margin_step = self.versus.margin_step();
+        for (player, _) in self
+            .players
+            .iter_mut()
+            .zip(self.alive.iter())
+            .filter(|(_, alive)| **alive)
+        {
+            player.set_margin(self.versus.margin_time(), margin_step);
+
+            if playing {
+                player.process_input(&time);
+            } else {
+                player.drop_input();
+            }
+        }
 
         // read the output and see if anything interesting happened in our game
-        let (one_lines, one_lost) = self.one.handle_events(|_| ());
-        let (two_lines, two_lost) = self.two.handle_events(|_| ());
+        let mut attacks = Vec::with_capacity(self.players.len());
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let (lines, lost) = player.handle_events(|_| ());
 
-        // update our lines from our opponents
-        self.one.handle_opponent_lines(two_lines);
-        self.two.handle_opponent_lines(one_lines);
+            if lost {
+                self.alive[i] = false;
+            }
+            if lines > 0 && playing {
+                attacks.push((i, lines));
+            }
+        }
 
-        if one_lost || two_lost {
-            panic!("Somebody won!")
+        // send our lines to our opponents
+        for (attacker, lines) in attacks {
+            if let Some(target) = self.choose_target(attacker) {
+                self.players[target].handle_opponent_lines(lines);
+            }
+        }
+
+        if playing && self.alive.iter().filter(|alive| **alive).count() <= 1 {
+            let winner = self.alive.iter().position(|alive| *alive);
+            self.versus.round_over(winner);
+        }
+
+        if self
+            .versus
+            .update(time.delta_seconds(), &mut ui_text, &mut game_over)
+        {
+            self.alive.iter_mut().for_each(|alive| *alive = true);
+            self.targets.iter_mut().for_each(|target| *target = None);
+            self.players.iter_mut().for_each(|player| player.restart());
         }
     }
 }
 
-pub fn setup<'a, 'b>(
-    _: GameType,
-    mut game_data: GameDataBuilder<'a, 'b>,
-) -> Result<GameDataBuilder<'a, 'b>, AmethystError> {
-    let (one_input_tx, one_input_rx) = channel::unbounded();
-    let (two_input_tx, two_input_rx) = channel::unbounded();
+pub fn setup<'w>(
+    game_type: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
+    let (player_count, targeting, bot_count, tbp_command) = match game_type {
+        GameType::Double {
+            players,
+            targeting,
+            bots,
+            tbp_command,
+        } => (players, targeting, bots, tbp_command),
+        _ => unreachable!(),
+    };
+    let human_count = player_count - bot_count;
+
+    let handicaps = Handicaps::load()?;
+    let bot_strength = BotStrength::load()?;
+
+    let mut input_txs = Vec::with_capacity(human_count);
+    let mut players = Vec::with_capacity(player_count);
 
-    let (one_in_tx, one_in_rx) = channel::unbounded();
-    let (one_out_tx, one_out_rx) = channel::unbounded();
+    let positions = layout(player_count);
+    for (i, &position) in positions.iter().enumerate() {
+        let (input_tx, input_rx) = channel::unbounded();
+        let (in_tx, in_rx) = channel::unbounded();
+        let (out_tx, out_rx) = channel::unbounded();
 
-    let (two_in_tx, two_in_rx) = channel::unbounded();
-    let (two_out_tx, two_out_rx) = channel::unbounded();
+        let handicap = handicaps.player(i);
+        game_data = game_data.with_system_desc(
+            TetrisGameSystemDesc {
+                position,
+                in_rx,
+                out_tx,
+                config: TetrisRenderingConfig {
+                    show_ghost: handicap.ghost,
+                    show_hold: handicap.hold,
+                    ..TetrisRenderingConfig::default()
+                },
+            },
+            &format!("game_system_player_{}", i + 1),
+            &[],
+        );
+
+        let player = LocalAttackPlayer::new(input_rx, in_tx, out_rx).with_handicap(handicap);
+        if i < human_count {
+            input_txs.push(input_tx);
+            players.push(player);
+        } else {
+            // our bots play through the same input as everyone else
+            players.push(match &tbp_command {
+                Some(command) => player.with_bot(TbpBot::spawn(command, input_tx)?),
+                None => player.with_bot(CpuBot::new(bot_strength, input_tx)),
+            });
+        }
+    }
 
     game_data = game_data
         .with_known_desc(InputSystemDesc {
-            one_input_tx,
-            two_input_tx: Some(two_input_tx),
+            input_txs,
+            overlays: positions[..human_count]
+                .iter()
+                .copied()
+                .map(beside)
+                .collect(),
         })
-        .with_system_desc(
-            TetrisGameSystemDesc {
-                position: (RENDERED_WIDTH + MARGIN * 2., MARGIN),
-                // position: (MARGIN, MARGIN),
-                in_rx: one_in_rx,
-                out_tx: one_out_tx,
-                config: TetrisRenderingConfig::default(),
+        .with_known_desc(DoublePlayerSystemDesc {
+            players,
+            positions,
+            targeting,
+            play_area: PlayArea {
+                width: (RENDERED_WIDTH + MARGIN) * player_count as f32 + MARGIN,
+                height: PIXEL_DIMENSION * VISIBLE_HEIGHT as f32 + MARGIN * 2.,
             },
-            "game_system_player_one",
-            &[KnownSystems::SpriteLoader.into()],
-        )
-        .with_system_desc(
-            TetrisGameSystemDesc {
-                // position: ((PIXEL_DIMENSION * BOARD_WIDTH as f32) + MARGIN * 2., MARGIN),
-                position: (MARGIN, MARGIN),
-                in_rx: two_in_rx,
-                out_tx: two_out_tx,
-                config: TetrisRenderingConfig::default(),
-            },
-            "game_system_player_two",
-            &[KnownSystems::SpriteLoader.into()],
-        )
-        .with_known(DoublePlayerSystem {
-            started: false,
-            one: LocalAttackPlayer::new(one_input_rx, one_in_tx, one_out_rx),
-            two: LocalAttackPlayer::new(two_input_rx, two_in_tx, two_out_rx),
         });
 
     Ok(game_data)
 }
 
+/// lay our boards out in a row, returning the position for each player
+fn layout(player_count: usize) -> Vec<(f32, f32)> {
+    let mut positions = vec![(0., 0.); player_count];
+
+    SCREEN_ORDER
+        .iter()
+        .filter(|&&player| player < player_count)
+        .enumerate()
+        .for_each(|(column, &player)| {
+            positions[player] = (MARGIN + (RENDERED_WIDTH + MARGIN) * column as f32, MARGIN);
+        });
+
+    positions
+}
+
+struct DoublePlayerSystemDesc {
+    players: Vec<LocalAttackPlayer>,
+    positions: Vec<(f32, f32)>,
+    targeting: TargetPolicy,
+    play_area: PlayArea,
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, DoublePlayerSystem> for DoublePlayerSystemDesc {
+    fn build(self, world: &mut World) -> DoublePlayerSystem {
+        <DoublePlayerSystem as System<'_>>::SystemData::setup(world);
+
+        world.insert(self.play_area);
+
+        // show any handicaps above their boards
+        for (player, position) in self.players.iter().zip(self.positions) {
+            player.handicap().show_label(position, world);
+        }
+
+        let player_count = self.players.len();
+        let versus = VersusMatch::new(player_names(player_count), world);
+        let garbage_timing = versus.garbage_timing();
+        let hints = Hints::fetch(world);
+
+        DoublePlayerSystem {
+            started: false,
+            players: self
+                .players
+                .into_iter()
+                .map(|player| player.with_garbage_timing(garbage_timing).with_hints(hints))
+                .collect(),
+            alive: vec![true; player_count],
+            targeting: self.targeting,
+            targets: vec![None; player_count],
+            versus,
+        }
+    }
+}
+
 impl KnownSystem<'_> for DoublePlayerSystem {
     fn name() -> KnownSystems {
         KnownSystems::ControlSystem
@@ -0,0 +1,244 @@
+use crate::events::{TetrisIn, UserInput};
+use crate::systems::tetris::{Board, Piece, PieceState};
+
+/// Follows a player's pieces through their valid input, counting moves their placements didn't need
+#[derive(Debug, Default)]
+pub struct Finesse {
+    /// our piece as it spawned and the board it spawned on
+    spawned: Option<PieceState>,
+    /// where our piece is now
+    piece: Option<Piece>,
+    moves: usize,
+    /// the tick, hold or swap that spawned our piece is still on its way to us
+    spawning: bool,
+    /// garbage moved our board under our piece, so we can't judge it
+    disturbed: bool,
+    faults: usize,
+    pieces: usize,
+}
+
+impl Finesse {
+    pub fn spawned(&mut self, state: &PieceState) {
+        self.piece = Some(state.piece.clone());
+        self.spawned = Some(state.clone());
+        self.moves = 0;
+        self.spawning = true;
+        self.disturbed = false;
+    }
+
+    /// follow an event our game accepted, see `TetrisOut::ValidIn`
+    pub fn valid_in(&mut self, event: &TetrisIn) {
+        let (board, piece) = match (&self.spawned, &mut self.piece) {
+            (Some(spawned), Some(piece)) => (&spawned.board, piece),
+            _ => return,
+        };
+
+        if self.spawning {
+            self.spawning = false;
+            return;
+        }
+
+        match event {
+            TetrisIn::User(input) if counts(*input) => {
+                self.moves += 1;
+
+                if let Some(moved) = piece.apply(*input, board) {
+                    *piece = moved;
+                }
+            }
+            // a blocked drop is about to lock our piece where it is
+            TetrisIn::User(UserInput::DropSoft) | TetrisIn::Tick => {
+                if let Some(moved) = piece.apply(UserInput::DropSoft, board) {
+                    *piece = moved;
+                }
+            }
+            TetrisIn::AddRows(_) | TetrisIn::PutBoard(_) => self.disturbed = true,
+            // hard drops lock before we hear about them and holds respawn, we catch both elsewhere
+            _ => (),
+        }
+    }
+
+    /// judge our piece as it locks, returning how many of its moves were wasted
+    pub fn locked(&mut self) -> usize {
+        let (spawned, piece) = match (self.spawned.take(), self.piece.take()) {
+            (Some(spawned), Some(piece)) => (spawned, piece),
+            _ => return 0,
+        };
+
+        if self.disturbed {
+            return 0;
+        }
+
+        // a piece resting on the stack lands where it is, otherwise it was hard dropped
+        let landed = piece.hard_drop(&spawned.board);
+        let faults = match moves_to(&spawned.board, &spawned.piece, &landed) {
+            Some(fewest) => self.moves.saturating_sub(fewest),
+            None => 0,
+        };
+
+        self.pieces += 1;
+        self.faults += faults;
+
+        faults
+    }
+
+    /// whether this input takes our piece closer to `target` along one of its shortest paths
+    pub fn leads_to(&self, input: UserInput, target: &Piece) -> bool {
+        let (board, piece) = match (&self.spawned, &self.piece) {
+            (Some(spawned), Some(piece)) => (&spawned.board, piece),
+            // nothing is falling, so there's nothing to get wrong
+            _ => return true,
+        };
+
+        match input {
+            UserInput::DropHard => same_place(&piece.hard_drop(board), target),
+            UserInput::Left | UserInput::Right | UserInput::RotateClockwise => {
+                // moves into the wall are common with fast repeats, so skip our search for them
+                let moved = match piece.apply(input, board) {
+                    Some(moved) => moved,
+                    None => return false,
+                };
+
+                match moves_to(board, piece, target) {
+                    Some(remaining) => {
+                        moves_to(board, &moved, target).map_or(false, |left| left < remaining)
+                    }
+                    None => false,
+                }
+            }
+            UserInput::DropSoft | UserInput::Hold => false,
+        }
+    }
+
+    pub fn faults(&self) -> usize {
+        self.faults
+    }
+
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+}
+
+/// the moves we're judged on, drops never count against us
+fn counts(input: UserInput) -> bool {
+    match input {
+        UserInput::Left | UserInput::Right | UserInput::RotateClockwise => true,
+        UserInput::DropSoft | UserInput::DropHard | UserInput::Hold => false,
+    }
+}
+
+/// the fewest moves that take our piece to land where `target` is
+fn moves_to(board: &Board, piece: &Piece, target: &Piece) -> Option<usize> {
+    board
+        .placements(piece)
+        .into_iter()
+        .find(|placement| same_place(&placement.piece, target))
+        .map(|placement| {
+            placement
+                .inputs
+                .into_iter()
+                .filter(|&input| counts(input))
+                .count()
+        })
+}
+
+fn same_place(one: &Piece, two: &Piece) -> bool {
+    let mut one: Vec<_> = one.board_pixels().collect();
+    let mut two: Vec<_> = two.board_pixels().collect();
+    one.sort_unstable();
+    two.sort_unstable();
+
+    one == two
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::tetris::Tetrimino;
+
+    use super::*;
+
+    fn spawn(finesse: &mut Finesse, board: &Board) -> Piece {
+        let piece = Piece::spawn(Tetrimino::T, 3);
+        finesse.spawned(&PieceState {
+            board: board.clone(),
+            piece: piece.clone(),
+            next: Tetrimino::I,
+            held: None,
+            can_hold: true,
+        });
+        // the tick that spawned our piece
+        finesse.valid_in(&TetrisIn::Tick);
+
+        piece
+    }
+
+    fn moved(piece: &Piece, inputs: &[UserInput], board: &Board) -> Piece {
+        inputs.iter().fold(piece.clone(), |piece, &input| {
+            piece
+                .apply(input, board)
+                .expect("Every input should move our piece")
+        })
+    }
+
+    #[test]
+    fn fewest_moves() {
+        let board = Board::new();
+        let piece = Piece::spawn(Tetrimino::T, 3);
+
+        let straight_down = piece.hard_drop(&board);
+        let left_wall = moved(&piece, &[UserInput::Left; 3], &board).hard_drop(&board);
+
+        assert_eq!(moves_to(&board, &piece, &straight_down), Some(0));
+        assert_eq!(moves_to(&board, &piece, &left_wall), Some(3));
+    }
+
+    #[test]
+    fn wasted_moves_are_faults() {
+        let board = Board::new();
+        let mut finesse = Finesse::default();
+        spawn(&mut finesse, &board);
+
+        for &input in &[UserInput::Left, UserInput::Right, UserInput::Left] {
+            finesse.valid_in(&TetrisIn::User(input));
+        }
+
+        assert_eq!(finesse.locked(), 2);
+        assert_eq!(finesse.faults(), 2);
+        assert_eq!(finesse.pieces(), 1);
+    }
+
+    #[test]
+    fn only_shortest_inputs_lead_to_target() {
+        let board = Board::new();
+        let mut finesse = Finesse::default();
+        let piece = spawn(&mut finesse, &board);
+        let target = moved(&piece, &[UserInput::Left], &board).hard_drop(&board);
+
+        assert!(finesse.leads_to(UserInput::Left, &target));
+        assert!(!finesse.leads_to(UserInput::Right, &target));
+        assert!(!finesse.leads_to(UserInput::RotateClockwise, &target));
+        assert!(!finesse.leads_to(UserInput::DropSoft, &target));
+        assert!(!finesse.leads_to(UserInput::DropHard, &target));
+
+        finesse.valid_in(&TetrisIn::User(UserInput::Left));
+
+        assert!(finesse.leads_to(UserInput::DropHard, &target));
+        assert!(!finesse.leads_to(UserInput::Left, &target));
+    }
+
+    #[test]
+    fn garbage_disturbs_our_piece() {
+        let board = Board::new();
+        let mut finesse = Finesse::default();
+        spawn(&mut finesse, &board);
+
+        for &input in &[UserInput::Left, UserInput::Right, UserInput::Left] {
+            finesse.valid_in(&TetrisIn::User(input));
+        }
+        finesse.valid_in(&TetrisIn::AddRows(2));
+
+        assert_eq!(finesse.locked(), 0);
+        assert_eq!(finesse.faults(), 0);
+        assert_eq!(finesse.pieces(), 0);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// How the garbage our opponents send makes its way onto our board
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GarbageTiming {
+    /// seconds garbage waits in our queue before it can enter our board
+    pub entry_delay: f32,
+    /// the most rows that can enter each time we lock a piece
+    pub per_lock_cap: usize,
+}
+
+impl Default for GarbageTiming {
+    fn default() -> Self {
+        GarbageTiming {
+            entry_delay: 0.5,
+            per_lock_cap: 8,
+        }
+    }
+}
+
+/// One attack waiting to enter our board
+#[derive(Debug)]
+struct Chunk {
+    lines: usize,
+    waited: f32,
+}
+
+/// Incoming garbage in the order it arrived
+#[derive(Debug, Default)]
+pub struct GarbageQueue {
+    timing: GarbageTiming,
+    chunks: VecDeque<Chunk>,
+}
+
+impl GarbageQueue {
+    pub fn new(timing: GarbageTiming) -> Self {
+        GarbageQueue {
+            timing,
+            chunks: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, lines: usize) {
+        if lines > 0 {
+            self.chunks.push_back(Chunk { lines, waited: 0. });
+        }
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        self.chunks
+            .iter_mut()
+            .for_each(|chunk| chunk.waited += delta_seconds);
+    }
+
+    /// cancel our oldest garbage first, returning whatever lines were left over
+    pub fn cancel(&mut self, mut lines: usize) -> usize {
+        while lines > 0 {
+            let chunk = match self.chunks.front_mut() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+
+            if chunk.lines > lines {
+                chunk.lines -= lines;
+                lines = 0;
+            } else {
+                lines -= chunk.lines;
+                self.chunks.pop_front();
+            }
+        }
+
+        lines
+    }
+
+    /// the rows that enter our board on this lock, oldest first and up to our cap
+    pub fn take_ready(&mut self) -> usize {
+        let mut rows = 0;
+
+        while rows < self.timing.per_lock_cap {
+            let chunk = match self.chunks.front_mut() {
+                Some(chunk) if chunk.waited >= self.timing.entry_delay => chunk,
+                _ => break,
+            };
+
+            let taken = chunk.lines.min(self.timing.per_lock_cap - rows);
+            rows += taken;
+            chunk.lines -= taken;
+
+            if chunk.lines == 0 {
+                self.chunks.pop_front();
+            }
+        }
+
+        rows
+    }
+
+    /// every row still waiting, ready or not
+    pub fn pending(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.lines).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(chunks: &[usize]) -> GarbageQueue {
+        let mut queue = GarbageQueue::new(GarbageTiming::default());
+        chunks.iter().for_each(|&lines| queue.push(lines));
+
+        queue
+    }
+
+    #[test]
+    fn partial_cancel_leaves_the_rest() {
+        let mut queue = queue(&[3, 4]);
+
+        assert_eq!(queue.cancel(5), 0);
+        assert_eq!(queue.pending(), 2);
+    }
+
+    #[test]
+    fn full_cancel_returns_leftover_lines() {
+        let mut queue = queue(&[3, 4]);
+
+        assert_eq!(queue.cancel(10), 3);
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn rows_wait_until_due() {
+        let mut queue = queue(&[2]);
+        queue.advance(0.3);
+
+        assert_eq!(queue.take_ready(), 0);
+        assert_eq!(queue.pending(), 2);
+
+        queue.push(3);
+        queue.advance(0.3);
+
+        // only our older chunk has waited long enough
+        assert_eq!(queue.take_ready(), 2);
+        assert_eq!(queue.pending(), 3);
+    }
+
+    #[test]
+    fn ready_rows_are_capped_per_lock() {
+        let mut queue = queue(&[5, 5]);
+        queue.advance(1.);
+
+        assert_eq!(queue.take_ready(), 8);
+        assert_eq!(queue.take_ready(), 2);
+        assert_eq!(queue.take_ready(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use amethyst::config::Config;
+use amethyst::ecs::{Entity, World};
+use amethyst::error::Error as AmethystError;
+use amethyst::ui::Anchor;
+use amethyst::utils::application_root_dir;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::states::camera_scale;
+use crate::systems::control::{ATTACK_LEVEL, MARGIN};
+use crate::systems::tetris::{PIXEL_DIMENSION, RENDERED_WIDTH, VISIBLE_HEIGHT};
+use crate::systems::ui_text::UiTextConfig;
+
+/// Evens out a versus match between players of different skill
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Handicap {
+    /// multiplies the garbage we send
+    pub attack: f32,
+    /// multiplies the garbage we receive
+    pub incoming: f32,
+    /// garbage we start every round with
+    pub starting_rows: usize,
+    /// our gravity level
+    pub level: usize,
+    pub ghost: bool,
+    pub hold: bool,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            attack: 1.,
+            incoming: 1.,
+            starting_rows: 0,
+            level: ATTACK_LEVEL,
+            ghost: true,
+            hold: true,
+        }
+    }
+}
+
+impl Handicap {
+    /// a short description of everything that differs from a fair game
+    pub fn label(&self) -> String {
+        let default = Handicap::default();
+        let mut parts = Vec::new();
+
+        if (self.attack - default.attack).abs() > f32::EPSILON {
+            parts.push(format!("Attack x{}", self.attack));
+        }
+        if (self.incoming - default.incoming).abs() > f32::EPSILON {
+            parts.push(format!("Incoming x{}", self.incoming));
+        }
+        if self.starting_rows != default.starting_rows {
+            parts.push(format!("+{} rows", self.starting_rows));
+        }
+        if self.level != default.level {
+            parts.push(format!("Level {}", self.level));
+        }
+        if !self.ghost {
+            parts.push("No ghost".to_string());
+        }
+        if !self.hold {
+            parts.push("No hold".to_string());
+        }
+
+        parts.join("  ")
+    }
+
+    /// show our label above the board at `position`, if we have one
+    pub fn show_label(&self, position: (f32, f32), world: &mut World) {
+        let label = self.label();
+        if label.is_empty() {
+            return;
+        }
+
+        label_above("handicap", position, &label, [1., 0., 0., 1.], world);
+    }
+}
+
+/// a line of text centered above the board at `position`, in the margin our handicaps are shown in
+pub(super) fn label_above(
+    id: &'static str,
+    (x, y): (f32, f32),
+    text: &str,
+    color: [f32; 4],
+    world: &mut World,
+) -> Entity {
+    let scale = camera_scale(world);
+    let config = UiTextConfig {
+        id,
+        width: 400.,
+        height: 20.,
+        anchor: Anchor::BottomLeft,
+        color,
+        ..UiTextConfig::default()
+    };
+    let center_x = (x + RENDERED_WIDTH / 2.) / scale;
+    let center_y = (y + PIXEL_DIMENSION * VISIBLE_HEIGHT as f32 + MARGIN / 2.) / scale;
+
+    UiTextConfig {
+        x: center_x - config.width / 2.,
+        y: center_y - config.height / 2.,
+        ..config
+    }
+    .build(text, world)
+}
+
+/// Our handicaps for each player in order, stored as RON next to our other config
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Handicaps {
+    players: Vec<Handicap>,
+}
+
+impl Handicaps {
+    /// load our handicaps from our config folder
+    pub fn load() -> Result<Handicaps, AmethystError> {
+        Ok(Handicaps::open(
+            application_root_dir()?
+                .join("resources")
+                .join("config")
+                .join("handicaps.ron"),
+        ))
+    }
+
+    /// load our handicaps, nobody gets one if the file is missing or unreadable
+    pub fn open<P: AsRef<Path>>(path: P) -> Handicaps {
+        if !path.as_ref().exists() {
+            return Handicaps::default();
+        }
+
+        <Handicaps as Config>::load(path).unwrap_or_else(|e| {
+            warn!("Playing without handicaps: {}", e);
+            Handicaps::default()
+        })
+    }
+
+    pub fn player(&self, index: usize) -> Handicap {
+        self.players.get(index).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fair_games_have_no_label() {
+        assert_eq!(Handicap::default().label(), "");
+    }
+
+    #[test]
+    fn label_lists_what_differs() {
+        let handicap = Handicap {
+            attack: 1.5,
+            starting_rows: 2,
+            hold: false,
+            ..Handicap::default()
+        };
+        assert_eq!(handicap.label(), "Attack x1.5  +2 rows  No hold");
+
+        let handicap = Handicap {
+            incoming: 0.5,
+            level: ATTACK_LEVEL + 2,
+            ghost: false,
+            ..Handicap::default()
+        };
+        assert_eq!(
+            handicap.label(),
+            format!("Incoming x0.5  Level {}  No ghost", ATTACK_LEVEL + 2)
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use amethyst::config::Config;
+use amethyst::ecs::World;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::control::bot::Search;
+
+/// Suggested placements outlined on our human players' boards, stored as RON next to our other config
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Hints {
+    pub enabled: bool,
+    /// how hard we think about each suggestion, holding is suggested by outlining the piece we'd get
+    pub search: Search,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        Hints {
+            enabled: false,
+            search: Search::default(),
+        }
+    }
+}
+
+impl Hints {
+    /// load our hints, falling back to the default if the file is missing or unreadable
+    pub fn open<P: AsRef<Path>>(path: P) -> Hints {
+        if !path.as_ref().exists() {
+            return Hints::default();
+        }
+
+        <Hints as Config>::load(path).unwrap_or_else(|e| {
+            warn!("Using the default hints: {}", e);
+            Hints::default()
+        })
+    }
+
+    /// the hints our game was started with
+    pub(super) fn fetch(world: &World) -> Hints {
+        world
+            .try_fetch::<Hints>()
+            .map(|hints| *hints)
+            .unwrap_or_default()
+    }
+}
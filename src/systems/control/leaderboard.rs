@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use amethyst::config::Config;
+use chrono::Local;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+const MAX_ENTRIES: usize = 10;
+
+/// The best local survival runs, stored as RON next to our other resources
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub date: String,
+    /// seconds survived
+    pub survival_time: f32,
+    pub lines_cleared: usize,
+}
+
+impl Leaderboard {
+    /// load our leaderboard, starting a fresh one if it's missing or unreadable
+    pub fn open<P: AsRef<Path>>(path: P) -> Leaderboard {
+        <Leaderboard as Config>::load(path).unwrap_or_else(|e| {
+            warn!("Starting a new leaderboard: {}", e);
+            Leaderboard::default()
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) {
+        if let Err(e) = self.write(path) {
+            warn!("Couldn't save the leaderboard: {}", e);
+        }
+    }
+
+    /// record a run and return its rank (starting at 1) if it made the board
+    pub fn record(&mut self, survival_time: f32, lines_cleared: usize) -> Option<usize> {
+        let entry = LeaderboardEntry {
+            date: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            survival_time,
+            lines_cleared,
+        };
+
+        // the first entry that we beat is where we belong
+        let index = self
+            .entries
+            .iter()
+            .position(|existing| compare(&entry, existing) == Ordering::Greater)
+            .unwrap_or_else(|| self.entries.len());
+
+        if index < MAX_ENTRIES {
+            self.entries.insert(index, entry);
+            self.entries.truncate(MAX_ENTRIES);
+
+            Some(index + 1)
+        } else {
+            None
+        }
+    }
+
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+}
+
+/// rank by time survived, breaking ties with the lines we cleared
+fn compare(a: &LeaderboardEntry, b: &LeaderboardEntry) -> Ordering {
+    a.survival_time
+        .partial_cmp(&b.survival_time)
+        .unwrap_or(Ordering::Equal)
+        .then(a.lines_cleared.cmp(&b.lines_cleared))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(survival_time: f32, lines_cleared: usize) -> LeaderboardEntry {
+        LeaderboardEntry {
+            date: String::new(),
+            survival_time,
+            lines_cleared,
+        }
+    }
+
+    fn times(leaderboard: &Leaderboard) -> Vec<f32> {
+        leaderboard
+            .entries()
+            .iter()
+            .map(|entry| entry.survival_time)
+            .collect()
+    }
+
+    #[test]
+    fn longer_runs_rank_higher() {
+        assert_eq!(compare(&entry(20., 0), &entry(10., 9)), Ordering::Greater);
+        assert_eq!(compare(&entry(10., 3), &entry(10., 5)), Ordering::Less);
+        assert_eq!(compare(&entry(10., 5), &entry(10., 5)), Ordering::Equal);
+    }
+
+    #[test]
+    fn record_returns_our_rank() {
+        let mut leaderboard = Leaderboard::default();
+
+        assert_eq!(leaderboard.record(10., 5), Some(1));
+        assert_eq!(leaderboard.record(20., 1), Some(1));
+        assert_eq!(leaderboard.record(15., 0), Some(2));
+        // a tie goes to the run with more lines, otherwise to whoever got there first
+        assert_eq!(leaderboard.record(10., 8), Some(3));
+        assert_eq!(leaderboard.record(10., 5), Some(5));
+
+        assert_eq!(times(&leaderboard), vec![20., 15., 10., 10., 10.]);
+    }
+
+    #[test]
+    fn full_leaderboard_drops_the_slowest() {
+        let mut leaderboard = Leaderboard::default();
+        for i in 0..MAX_ENTRIES {
+            leaderboard.record(100. + i as f32, 0);
+        }
+
+        assert_eq!(leaderboard.record(1., 0), None);
+        assert_eq!(leaderboard.record(150., 0), Some(1));
+
+        assert_eq!(leaderboard.entries().len(), MAX_ENTRIES);
+        assert_eq!(times(&leaderboard)[MAX_ENTRIES - 1], 101.);
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// Guideline style margin time, once a round runs long gravity and attacks ramp up until it ends
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MarginTime {
+    /// seconds into a round before anything changes
+    pub delay: f32,
+    /// seconds between each step after our delay
+    pub interval: f32,
+    /// gravity levels added every step
+    pub levels_per_step: usize,
+    /// added to our attack multiplier every step
+    pub attack_per_step: f32,
+    pub max_steps: usize,
+}
+
+impl Default for MarginTime {
+    fn default() -> Self {
+        MarginTime {
+            delay: 60.,
+            interval: 20.,
+            levels_per_step: 1,
+            attack_per_step: 0.25,
+            max_steps: 10,
+        }
+    }
+}
+
+impl MarginTime {
+    /// how many steps we've taken this far into a round
+    pub fn step(&self, elapsed: f32) -> usize {
+        if elapsed < self.delay {
+            return 0;
+        }
+
+        let steps = 1 + ((elapsed - self.delay) / self.interval.max(1.)) as usize;
+        steps.min(self.max_steps)
+    }
+
+    pub fn levels(&self, step: usize) -> usize {
+        step * self.levels_per_step
+    }
+
+    pub fn attack_multiplier(&self, step: usize) -> f32 {
+        1. + step as f32 * self.attack_per_step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_step_lands_on_our_delay() {
+        let margin_time = MarginTime::default();
+
+        assert_eq!(margin_time.step(0.), 0);
+        assert_eq!(margin_time.step(59.9), 0);
+        assert_eq!(margin_time.step(60.), 1);
+    }
+
+    #[test]
+    fn steps_grow_every_interval_up_to_our_max() {
+        let margin_time = MarginTime::default();
+
+        assert_eq!(margin_time.step(79.9), 1);
+        assert_eq!(margin_time.step(80.), 2);
+        assert_eq!(margin_time.step(120.), 4);
+        assert_eq!(margin_time.step(10_000.), margin_time.max_steps);
+    }
+
+    #[test]
+    fn steps_ramp_gravity_and_attack() {
+        let margin_time = MarginTime::default();
+
+        assert_eq!(margin_time.levels(0), 0);
+        assert_eq!(margin_time.levels(3), 3);
+        assert!((margin_time.attack_multiplier(0) - 1.).abs() < f32::EPSILON);
+        assert!((margin_time.attack_multiplier(4) - 2.).abs() < f32::EPSILON);
+    }
+}
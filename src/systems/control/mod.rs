@@ -1,16 +1,37 @@
 use std::net::SocketAddr;
 
+use amethyst::ecs::World;
 use amethyst::error::Error as AmethystError;
-use amethyst::GameDataBuilder;
 
+pub use double_player_system::{TargetPolicy, MAX_LOCAL_PLAYERS};
+pub use garbage_queue::GarbageTiming;
+pub use hint::Hints;
+pub use margin_time::MarginTime;
 pub use player::*;
+pub use swap_system::SwapTrigger;
+pub use versus_match::MatchConfig;
 
 use crate::systems::tetris::PIXEL_DIMENSION;
+use crate::systems::utils::SystemsBuilder;
 
+mod bot;
+mod coop_player_system;
 mod double_player_system;
+mod finesse;
+mod garbage_queue;
+mod handicap;
+mod hint;
+mod leaderboard;
+mod margin_time;
 mod multiplayer_system;
 mod player;
+mod relay_system;
+mod simulation;
 mod single_player_system;
+mod survival_system;
+mod swap_system;
+mod tbp;
+mod versus_match;
 
 pub const MARGIN: f32 = PIXEL_DIMENSION / 2. + 20.;
 const ATTACK_LEVEL: usize = 3;
@@ -18,22 +39,55 @@ const ATTACK_LEVEL: usize = 3;
 #[derive(Clone, Debug)]
 pub enum GameType {
     Single,
-    Double,
+    /// single player with a target for every piece, only accepting the shortest inputs there
+    Trainer,
+    /// a local battle between 2 to 4 players, the last `bots` of them played by the CPU
+    Double {
+        players: usize,
+        targeting: TargetPolicy,
+        bots: usize,
+        /// a command starting a Tetris Bot Protocol bot to play our bots' slots instead of ours
+        tbp_command: Option<String>,
+    },
+    Survival,
     CoOp,
-    Server(String),
-    Client(SocketAddr),
+    /// players take turns every N pieces on a shared board
+    Relay(usize),
+    /// versus where the players trade boards
+    Swap(SwapTrigger),
+    /// network versus, `bot` lets the CPU play our side
+    Server {
+        address: String,
+        bot: bool,
+    },
+    Client {
+        address: SocketAddr,
+        bot: bool,
+    },
 }
 
 impl GameType {
-    pub fn setup<'a, 'b>(
+    pub fn setup<'w>(
         self,
-        game_data: GameDataBuilder<'a, 'b>,
-    ) -> Result<GameDataBuilder<'a, 'b>, AmethystError> {
+        game_data: SystemsBuilder<'w>,
+    ) -> Result<SystemsBuilder<'w>, AmethystError> {
         match self {
-            GameType::Single => single_player_system::setup(self, game_data),
-            GameType::Double => double_player_system::setup(self, game_data),
-            GameType::CoOp => todo!("add a coop mode"),
-            GameType::Server(_) | GameType::Client(_) => multiplayer_system::setup(self, game_data),
+            GameType::Single | GameType::Trainer => single_player_system::setup(self, game_data),
+            GameType::Double { .. } => double_player_system::setup(self, game_data),
+            GameType::Survival => survival_system::setup(self, game_data),
+            GameType::CoOp => coop_player_system::setup(self, game_data),
+            GameType::Relay(_) => relay_system::setup(self, game_data),
+            GameType::Swap(_) => swap_system::setup(self, game_data),
+            GameType::Server { .. } | GameType::Client { .. } => {
+                multiplayer_system::setup(self, game_data)
+            }
+        }
+    }
+
+    /// clean up any resources our systems left in the world
+    pub fn teardown(&self, world: &mut World) {
+        if let GameType::Server { .. } | GameType::Client { .. } = self {
+            multiplayer_system::teardown(world);
         }
     }
 }
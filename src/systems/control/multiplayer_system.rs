@@ -1,106 +1,150 @@
-use amethyst::core::ecs::shrev::EventChannel;
 use amethyst::core::Time;
-use amethyst::ecs::{Read, ReaderId, System, SystemData, World};
+use amethyst::ecs::{Read, System, SystemData, World, Write, WriteStorage};
 use amethyst::error::Error as AmethystError;
-use amethyst::network::simulation::laminar::{LaminarNetworkBundle, LaminarSocket};
-use amethyst::network::simulation::NetworkSimulationEvent;
+use amethyst::network::simulation::laminar::{
+    LaminarNetworkBundle, LaminarSocket, LaminarSocketResource,
+};
 use amethyst::prelude::*;
-use amethyst::GameDataBuilder;
+use amethyst::ui::UiText;
 use crossbeam::channel;
 use crossbeam::channel::{Receiver, Sender};
 use log::info;
 
-use crate::events::{TetrisIn, TetrisOut, UserInput};
-use crate::systems::control::{sent_pieces, LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::events::{TetrisIn, TetrisOut, TimedInput};
+use crate::systems::control::bot::{BotStrength, CpuBot};
+use crate::systems::control::handicap::{Handicap, Handicaps};
+use crate::systems::control::versus_match::VersusMatch;
+use crate::systems::control::{Hints, LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::systems::input_display::beside;
 use crate::systems::input_system::InputSystemDesc;
 use crate::systems::network::client_system::ClientSystemDesc;
 use crate::systems::network::server_system::ServerSystemDesc;
 use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
 use crate::systems::tetris::RENDERED_WIDTH;
-use crate::systems::utils::{KnownSystem, WithKnownSystemDesc};
-use crate::systems::{GameType, KnownSystems};
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystemDesc};
+use crate::systems::{
+    GameType, KnownSystems, NetworkAttack, NetworkMargin, NetworkPause, NetworkRound, NetworkStart,
+};
 use crate::ExpectSender;
+use crate::GameOver;
 
+/// We only start running once our countdown is done, which both sides schedule together
 struct MultiplayerSystem {
-    state: State,
+    started: bool,
+    /// the server decides who won each round, when the next one starts and how far into margin
+    /// time we are, so both sides score and speed up together
+    server: bool,
     local_player: LocalAttackPlayer,
     player_net_tx: Sender<TetrisIn>,
     opponent_rx: Receiver<TetrisOut>,
-    reader: ReaderId<NetworkSimulationEvent>,
+    versus: VersusMatch,
 }
 
-#[derive(Copy, Clone, Debug)]
-enum State {
-    Started,
-    ClientWaiting,
-    ServerWaiting,
-}
-
-// TODO create an event system for the entire game, we can reuse it for different things
-// start game: send start events
-// won
-// lost
-// etc
 impl<'s> System<'s> for MultiplayerSystem {
     type SystemData = (
-        Read<'s, EventChannel<NetworkSimulationEvent>>,
         // track when we should emit Tick events
         Read<'s, Time>,
+        WriteStorage<'s, UiText>,
+        Write<'s, GameOver>,
+        Write<'s, NetworkMargin>,
+        Write<'s, NetworkStart>,
+        Write<'s, NetworkRound>,
+        Write<'s, NetworkAttack>,
+        Read<'s, NetworkPause>,
     );
 
-    fn run(&mut self, (net_events, time): Self::SystemData) {
-        match self.state {
-            State::Started => {
-                // drop all of our network events (we only needed the Connect event)
-                net_events.read(&mut self.reader);
+    fn run(&mut self, data: Self::SystemData) {
+        let (time, mut ui_text, mut game_over, mut margin, mut start, mut round, mut attack, pause) =
+            data;
 
-                self.local_player.process_input(&time);
+        if pause.opponent_left() {
+            if game_over.summary.is_none() {
+                game_over.summary = Some("Your opponent left".to_string());
             }
-            State::ClientWaiting => {
-                // we're a client so we can kick off our start event immediately
-                self.state = State::Started;
+            return;
+        }
 
-                self.local_player.start_game();
-            }
-            State::ServerWaiting => {
-                // either we've started a client and will never get this event, or we're a server waiting for it
-                for net_event in net_events.read(&mut self.reader) {
-                    if let NetworkSimulationEvent::Connect(_) = net_event {
-                        self.state = State::Started;
-
-                        self.local_player.start_game();
-                    }
-                }
-            }
+        if !self.started {
+            self.started = true;
+
+            self.local_player.start_game();
+        }
+
+        if self.server {
+            margin.set_step(self.versus.margin_step());
+        }
+        self.local_player
+            .set_margin(self.versus.margin_time(), margin.step());
+
+        if self.versus.playing() {
+            self.local_player.process_input(&time);
+        } else {
+            self.local_player.drop_input();
         }
 
         let net_tx = self.player_net_tx.clone();
-        let (_, local_lost) = self
+        let (lines, local_lost) = self
             .local_player
             .handle_events(|in_event| net_tx.send_expect(in_event));
 
+        // our attack goes out already cancelled and scaled, our opponent only scales what comes in
+        let incoming = attack.take_incoming();
+        if self.versus.playing() {
+            attack.send(lines);
+            if incoming > 0 {
+                self.local_player.handle_opponent_lines(incoming);
+            }
+        }
+
         // read the opponent output and see if anything interesting happened in our game
         let mut remote_lost = false;
         while let Ok(opponent_event) = self.opponent_rx.try_recv() {
-            match opponent_event {
-                TetrisOut::RemovedRows(rows) => {
-                    self.local_player.handle_opponent_lines(sent_pieces(rows));
-                }
-                TetrisOut::Lose => remote_lost = true,
-                _ => (),
+            if let TetrisOut::Lose = opponent_event {
+                remote_lost = true;
             }
         }
 
-        if local_lost || remote_lost {
-            panic!("Somebody won!")
+        // our games' losses reach each side in a different order, so only our server's view counts
+        let result = if self.server && self.versus.playing() {
+            let result = match (local_lost, remote_lost) {
+                (false, false) => None,
+                (true, true) => Some(None),
+                (false, true) => Some(Some(0)),
+                (true, false) => Some(Some(1)),
+            };
+            if let Some(winner) = result {
+                round.decide(winner);
+            }
+            result
+        } else {
+            round.take_result()
+        };
+        if let Some(winner) = result {
+            self.versus.round_over(winner);
+        }
+
+        // every round starts on our server's schedule, with the same countdown on both sides
+        if let Some(countdown) = start.take_countdown() {
+            self.versus.start_in(countdown);
+        }
+        if self.server && self.versus.waiting_to_start() {
+            start.request();
+        }
+
+        // our new seed goes out with our Start event so our opponent's mirror follows along
+        if self
+            .versus
+            .update(time.delta_seconds(), &mut ui_text, &mut game_over)
+        {
+            self.local_player.restart();
         }
     }
 }
 
-pub fn setup<'a, 'b>(
+pub fn setup<'w>(
     game_type: GameType,
-    mut game_data: GameDataBuilder<'a, 'b>,
-) -> Result<GameDataBuilder<'a, 'b>, AmethystError> {
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
     let (input_out_tx, input_out_rx) = channel::unbounded();
 
     let (player_in_tx, player_in_rx) = channel::unbounded();
@@ -111,20 +155,44 @@ pub fn setup<'a, 'b>(
     let (opponent_in_net_tx, opponent_in_net_rx) = channel::unbounded();
     let (opponent_out_tx, opponent_out_rx) = channel::unbounded();
 
+    // each side plays with its own first handicap, our opponent's mirror follows whatever it does
+    let handicap = Handicaps::load()?.player(0);
+
+    let (server, bot) = match &game_type {
+        GameType::Server { bot, .. } => (true, *bot),
+        GameType::Client { bot, .. } => (false, *bot),
+        _ => unreachable!(),
+    };
+
+    // our bot plays through the same input our keys would
+    let (input_txs, overlays, bot) = if bot {
+        (
+            vec![],
+            vec![],
+            Some(CpuBot::new(BotStrength::load()?, input_out_tx)),
+        )
+    } else {
+        (vec![input_out_tx], vec![beside((MARGIN, MARGIN))], None)
+    };
+
     game_data = game_data
         .with_known_desc(InputSystemDesc {
-            one_input_tx: input_out_tx,
-            two_input_tx: None,
+            input_txs,
+            overlays,
         })
         .with_system_desc(
             TetrisGameSystemDesc {
                 position: (MARGIN, MARGIN),
                 in_rx: player_in_rx,
                 out_tx: player_out_tx,
-                config: TetrisRenderingConfig::default(),
+                config: TetrisRenderingConfig {
+                    show_ghost: handicap.ghost,
+                    show_hold: handicap.hold,
+                    ..TetrisRenderingConfig::default()
+                },
             },
             "game_system_player",
-            &[KnownSystems::SpriteLoader.into()],
+            &[],
         )
         .with_system_desc(
             TetrisGameSystemDesc {
@@ -138,38 +206,37 @@ pub fn setup<'a, 'b>(
                 },
             },
             "game_system_opponent",
-            &[KnownSystems::SpriteLoader.into()],
+            &[],
         );
 
-    let state;
     game_data = match game_type {
-        GameType::Server(address) => {
+        GameType::Server { address, .. } => {
             info!("Server listening on: {}", address);
 
-            state = State::ServerWaiting;
-
             let socket = LaminarSocket::bind(address)?;
             // let listener = TcpListener::bind(address)?;
             //listener.set_nonblocking(true)?;
 
+            // our connection keeps going while we're paused or counting down
             game_data
-                .with_bundle(LaminarNetworkBundle::new(Some(socket)))?
+                .with_background_bundle(LaminarNetworkBundle::new(Some(socket)))?
                 // .with_bundle(TcpNetworkBundle::new(Some(listener), 2048))?
-                .with_known_desc(ServerSystemDesc {
+                .with_background_known_desc(ServerSystemDesc {
                     player_out_tx: player_out_net_rx,
                     opponent_in_rx: opponent_in_net_tx,
                 })
         }
-        GameType::Client(server_address) => {
-            state = State::ClientWaiting;
-
+        GameType::Client {
+            address: server_address,
+            ..
+        } => {
             // make sure we're binding a socket on our external interface
             let socket = LaminarSocket::bind("0.0.0.0:0")?;
 
             game_data
-                .with_bundle(LaminarNetworkBundle::new(Some(socket)))?
+                .with_background_bundle(LaminarNetworkBundle::new(Some(socket)))?
                 //.with_bundle(TcpNetworkBundle::new(None, 2048))?
-                .with_known_desc(ClientSystemDesc {
+                .with_background_known_desc(ClientSystemDesc {
                     player_out_tx: player_out_net_rx,
                     opponent_in_rx: opponent_in_net_tx,
                     server_address,
@@ -179,39 +246,63 @@ pub fn setup<'a, 'b>(
     };
 
     Ok(game_data.with_known_desc(MultiplayerSystemDesc {
-        state,
-
         input_rx: input_out_rx,
         player_tx: player_in_tx,
         player_rx: player_out_rx,
         player_net_tx: player_out_net_tx,
         opponent_rx: opponent_out_rx,
+        server,
+        handicap,
+        bot,
     }))
 }
 
+/// drop our socket so the next game can bind it again
+pub fn teardown(world: &mut World) {
+    world.remove::<LaminarSocketResource>();
+    world.remove::<NetworkPause>();
+    world.remove::<NetworkStart>();
+    world.remove::<NetworkMargin>();
+    world.remove::<NetworkRound>();
+    world.remove::<NetworkAttack>();
+}
+
 pub struct MultiplayerSystemDesc {
-    state: State,
-    input_rx: Receiver<UserInput>,
+    input_rx: Receiver<TimedInput>,
     player_tx: Sender<TetrisIn>,
     player_rx: Receiver<TetrisOut>,
     player_net_tx: Sender<TetrisIn>,
     opponent_rx: Receiver<TetrisOut>,
+    server: bool,
+    handicap: Handicap,
+    bot: Option<CpuBot>,
 }
 
 impl<'a, 'b> SystemDesc<'a, 'b, MultiplayerSystem> for MultiplayerSystemDesc {
     fn build(self, world: &mut World) -> MultiplayerSystem {
         <MultiplayerSystem as System<'_>>::SystemData::setup(world);
 
-        let reader_id = world
-            .fetch_mut::<EventChannel<NetworkSimulationEvent>>()
-            .register_reader();
+        self.handicap.show_label((MARGIN, MARGIN), world);
+
+        let name = if self.bot.is_some() { "CPU" } else { "You" };
+        let versus = VersusMatch::new(vec![name.to_string(), "Opponent".to_string()], world)
+            .with_scheduled_rounds();
+
+        let mut local_player =
+            LocalAttackPlayer::new(self.input_rx, self.player_tx, self.player_rx)
+                .with_handicap(self.handicap)
+                .with_garbage_timing(versus.garbage_timing());
+        if let Some(bot) = self.bot {
+            local_player = local_player.with_bot(bot);
+        }
 
         MultiplayerSystem {
-            state: self.state,
-            local_player: LocalAttackPlayer::new(self.input_rx, self.player_tx, self.player_rx),
+            started: false,
+            server: self.server,
+            local_player: local_player.with_hints(Hints::fetch(world)),
             player_net_tx: self.player_net_tx,
             opponent_rx: self.opponent_rx,
-            reader: reader_id,
+            versus,
         }
     }
 }
@@ -1,17 +1,36 @@
 use amethyst::core::Time;
 use crossbeam::channel::{Receiver, Sender};
+use log::debug;
 use rand::Rng;
 
-use crate::events::{TetrisIn, TetrisOut, UserInput};
-use crate::systems::control::{sent_pieces, ATTACK_LEVEL};
+use crate::events::{TetrisIn, TetrisOut, TimedInput, UserInput};
+use crate::systems::control::bot::{Bot, Search};
+use crate::systems::control::finesse::Finesse;
+use crate::systems::control::garbage_queue::{GarbageQueue, GarbageTiming};
+use crate::systems::control::handicap::Handicap;
+use crate::systems::control::hint::Hints;
+use crate::systems::control::margin_time::MarginTime;
+use crate::systems::control::sent_pieces;
+use crate::systems::control::simulation::{Simulation, STEP};
+use crate::systems::tetris::{BoardState, Piece, PieceState};
 use crate::ExpectSender;
 
+// our trainer's targets can always be reached with nothing but shifts, rotations and a hard drop
+const TRAINER_SEARCH: Search = Search {
+    look_ahead: true,
+    hold: false,
+    tucks: false,
+};
+
+// our gravity never ticks faster than this, however high our level goes
+const MIN_TICK_SECONDS: f32 = 0.001;
+
 pub trait LocalPlayer {
     fn level(&self) -> usize;
 
-    fn tick_timer(&mut self) -> &mut f32;
+    fn simulation(&mut self) -> &mut Simulation;
 
-    fn input_rx(&self) -> &Receiver<UserInput>;
+    fn input_rx(&self) -> &Receiver<TimedInput>;
     fn tetris_tx(&self) -> &Sender<TetrisIn>;
     fn tetris_rx(&self) -> &Receiver<TetrisOut>;
 
@@ -20,19 +39,58 @@ pub trait LocalPlayer {
         self.tetris_tx().send_expect(TetrisIn::Start(seed));
     }
 
+    /// whether we pass this input along to our game
+    fn accepts(&mut self, _input: UserInput) -> bool {
+        true
+    }
+
+    /// keep track of time passing while we're playing
+    fn advance(&mut self, _time: &Time) {}
+
+    /// throw away any input that came in while we weren't playing
+    fn drop_input(&mut self) {
+        while self.input_rx().try_recv().is_ok() {}
+        self.simulation().clear();
+    }
+
     fn process_input(&mut self, time: &Time) {
-        // forward all of our input events
-        while let Ok(input_event) = self.input_rx().try_recv() {
-            self.tetris_tx().send_expect(TetrisIn::User(input_event))
+        self.advance(time);
+
+        // our bots and keys send separately, our simulation puts it all back in the order it happened
+        let inputs: Vec<TimedInput> = self.input_rx().try_iter().collect();
+        self.simulation().queue(inputs);
+
+        let steps = self
+            .simulation()
+            .steps(time.absolute_time(), time.delta_time());
+        for step_end in steps {
+            // forward the input that came in before this step ends, then let gravity catch up
+            for input in self.simulation().inputs_before(step_end) {
+                if self.accepts(input) {
+                    self.tetris_tx().send_expect(TetrisIn::User(input))
+                }
+            }
+
+            self.step_gravity();
         }
+    }
+
+    /// run our gravity for one step, ticking every time it comes due
+    fn step_gravity(&mut self) {
+        let level_float = self.level() as f32 - 1.;
+        let interval = (0.8 - (level_float * 0.007))
+            .powf(level_float)
+            .max(MIN_TICK_SECONDS);
 
-        // see if we need to forward a tick event
-        *self.tick_timer() -= time.delta_seconds();
-        if *self.tick_timer() <= 0. {
-            let level_float = self.level() as f32 - 1.;
-            *self.tick_timer() = (0.8 - (level_float * 0.007)).powf(level_float);
+        let tick_timer = &mut self.simulation().tick_timer;
+        *tick_timer -= STEP.as_secs_f32();
+        let mut ticks = 0;
+        while *tick_timer <= 0. {
+            *tick_timer += interval;
+            ticks += 1;
+        }
 
-            // send our tick event
+        for _ in 0..ticks {
             self.tetris_tx().send_expect(TetrisIn::Tick);
         }
     }
@@ -40,30 +98,80 @@ pub trait LocalPlayer {
 
 pub struct SinglePlayer {
     level: usize,
-    tick_timer: f32,
-    input_rx: Receiver<UserInput>,
+    simulation: Simulation,
+    finesse: Finesse,
+    /// whether we only let through inputs on the shortest way to our target
+    training: bool,
+    /// where our trainer wants our current piece
+    target: Option<Piece>,
+    rejected_inputs: usize,
+    /// the input we last rejected, so a held key only counts against us once
+    last_rejected: Option<UserInput>,
+    hints: Option<Search>,
+    input_rx: Receiver<TimedInput>,
     tetris_tx: Sender<TetrisIn>,
     tetris_rx: Receiver<TetrisOut>,
 }
 
 impl SinglePlayer {
     pub fn new(
-        input_rx: Receiver<UserInput>,
+        input_rx: Receiver<TimedInput>,
         tetris_tx: Sender<TetrisIn>,
         tetris_rx: Receiver<TetrisOut>,
     ) -> SinglePlayer {
         SinglePlayer {
             level: 0,
-            tick_timer: 0.,
+            simulation: Simulation::default(),
+            finesse: Finesse::default(),
+            training: false,
+            target: None,
+            rejected_inputs: 0,
+            last_rejected: None,
+            hints: None,
             input_rx,
             tetris_tx,
             tetris_rx,
         }
     }
 
+    /// show a target for every piece and reject any input that doesn't take the shortest way there
+    pub fn with_trainer(mut self) -> Self {
+        self.training = true;
+        self
+    }
+
+    /// outline a suggested placement for each of our pieces
+    pub fn with_hints(mut self, hints: Hints) -> Self {
+        self.hints = Some(hints.search).filter(|_| hints.enabled);
+        self
+    }
+
     pub fn handle_events(&mut self) -> bool {
         while let Ok(game_event) = self.tetris_rx.try_recv() {
             match game_event {
+                TetrisOut::ValidIn(event) => self.finesse.valid_in(&event),
+                TetrisOut::Spawned(state) => {
+                    self.finesse.spawned(&state);
+
+                    if self.training {
+                        self.target = TRAINER_SEARCH
+                            .choose(&state)
+                            .placement
+                            .map(|placement| placement.piece);
+                        self.tetris_tx
+                            .send_expect(TetrisIn::Outline(self.target.clone()));
+                    } else {
+                        show_hint(self.hints, &state, &self.tetris_tx);
+                    }
+                }
+                TetrisOut::LockedPiece => {
+                    let faults = self.finesse.locked();
+                    if faults > 0 {
+                        debug!("{} finesse faults on that piece", faults);
+                    }
+
+                    self.target = None;
+                }
                 TetrisOut::RemovedRows(_rows) => {}
                 TetrisOut::Lose => return true,
                 _ => (),
@@ -72,6 +180,21 @@ impl SinglePlayer {
 
         false
     }
+
+    /// how we're doing at placing our pieces efficiently
+    pub fn finesse_summary(&self) -> String {
+        let summary = format!(
+            "{} finesse faults in {} pieces",
+            self.finesse.faults(),
+            self.finesse.pieces()
+        );
+
+        if self.training {
+            format!("{}, {} inputs rejected", summary, self.rejected_inputs)
+        } else {
+            summary
+        }
+    }
 }
 
 impl LocalPlayer for SinglePlayer {
@@ -79,11 +202,27 @@ impl LocalPlayer for SinglePlayer {
         self.level
     }
 
-    fn tick_timer(&mut self) -> &mut f32 {
-        &mut self.tick_timer
+    fn accepts(&mut self, input: UserInput) -> bool {
+        let accepted = match &self.target {
+            Some(target) => self.finesse.leads_to(input, target),
+            None => true,
+        };
+
+        if accepted {
+            self.last_rejected = None;
+        } else if self.last_rejected != Some(input) {
+            self.last_rejected = Some(input);
+            self.rejected_inputs += 1;
+        }
+
+        accepted
+    }
+
+    fn simulation(&mut self) -> &mut Simulation {
+        &mut self.simulation
     }
 
-    fn input_rx(&self) -> &Receiver<UserInput> {
+    fn input_rx(&self) -> &Receiver<TimedInput> {
         &self.input_rx
     }
 
@@ -97,22 +236,49 @@ impl LocalPlayer for SinglePlayer {
 }
 
 pub struct LocalAttackPlayer {
-    pending_lines: usize,
-    tick_timer: f32,
-    input_rx: Receiver<UserInput>,
+    handicap: Handicap,
+    /// what margin time has added on top of our handicap this round
+    margin_levels: usize,
+    margin_attack: f32,
+    /// the fractions of a line our handicap left over from sending and receiving
+    attack_remainder: f32,
+    incoming_remainder: f32,
+    garbage: GarbageQueue,
+    /// the pending garbage our meter is showing
+    shown_garbage: usize,
+    lines_cleared: usize,
+    locked_pieces: usize,
+    /// our board after we've been asked to hand it over
+    board_state: Option<BoardState>,
+    /// plays for us by feeding our input, if we're a CPU player
+    bot: Option<Box<dyn Bot>>,
+    hints: Option<Search>,
+    simulation: Simulation,
+    input_rx: Receiver<TimedInput>,
     tetris_tx: Sender<TetrisIn>,
     tetris_rx: Receiver<TetrisOut>,
 }
 
 impl LocalAttackPlayer {
     pub fn new(
-        input_rx: Receiver<UserInput>,
+        input_rx: Receiver<TimedInput>,
         tetris_tx: Sender<TetrisIn>,
         tetris_rx: Receiver<TetrisOut>,
     ) -> LocalAttackPlayer {
         LocalAttackPlayer {
-            pending_lines: 0,
-            tick_timer: 0.,
+            handicap: Handicap::default(),
+            margin_levels: 0,
+            margin_attack: 1.,
+            attack_remainder: 0.,
+            incoming_remainder: 0.,
+            garbage: GarbageQueue::default(),
+            shown_garbage: 0,
+            lines_cleared: 0,
+            locked_pieces: 0,
+            board_state: None,
+            bot: None,
+            hints: None,
+            simulation: Simulation::default(),
             input_rx,
             tetris_tx,
             tetris_rx,
@@ -125,55 +291,181 @@ impl LocalAttackPlayer {
         F: FnMut(TetrisIn),
     {
         let mut to_send_pieces = 0;
+        let mut locked = false;
         while let Ok(game_event) = self.tetris_rx.try_recv() {
             match game_event {
                 TetrisOut::ValidIn(in_event) => input_handler(in_event),
                 TetrisOut::RemovedRows(rows) => {
-                    let mut lines = sent_pieces(rows);
+                    self.lines_cleared += rows;
 
-                    if lines > self.pending_lines {
-                        lines -= self.pending_lines;
-                        self.pending_lines = 0;
-                    } else {
-                        self.pending_lines -= lines;
-                        lines = 0;
-                    }
+                    let lines = scale_lines(
+                        sent_pieces(rows),
+                        self.handicap.attack * self.margin_attack,
+                        &mut self.attack_remainder,
+                    );
 
-                    if lines > 0 {
-                        to_send_pieces += lines;
-                    }
+                    // our attack cancels our own garbage before anything goes out
+                    to_send_pieces += self.garbage.cancel(lines);
                 }
                 TetrisOut::LockedPiece => {
-                    // we locked a piece so we can send all of our pending pieces
-                    if self.pending_lines > 0 {
-                        self.tetris_tx
-                            .send(TetrisIn::AddRows(self.pending_lines))
-                            .expect("Always send");
-                        self.pending_lines = 0;
+                    self.locked_pieces += 1;
+                    locked = true;
+                }
+                TetrisOut::Board(state) => self.board_state = Some(state),
+                TetrisOut::Spawned(mut state) => {
+                    // we'd never get to make a hold our handicap takes away, so don't plan one
+                    state.can_hold &= self.handicap.hold;
+
+                    if let Some(bot) = &mut self.bot {
+                        bot.plan(&state);
                     }
+
+                    show_hint(self.hints, &state, &self.tetris_tx);
                 }
                 TetrisOut::Lose => return (0, true),
             }
         }
 
+        // our lock's line clears show up after it, so only let garbage in once they've cancelled
+        if locked {
+            let rows = self.garbage.take_ready();
+            if rows > 0 {
+                self.tetris_tx.send_expect(TetrisIn::AddRows(rows));
+            }
+        }
+        self.show_garbage();
+
         (to_send_pieces, false)
     }
 
+    /// let a bot play for us, its inputs should show up on our `input_rx`
+    pub fn with_bot<B: Bot + 'static>(mut self, bot: B) -> Self {
+        self.bot = Some(Box::new(bot));
+
+        self
+    }
+
+    /// outline a suggested placement for each of our pieces, bots play without them
+    pub fn with_hints(mut self, hints: Hints) -> Self {
+        self.hints = Some(hints.search).filter(|_| hints.enabled && self.bot.is_none());
+
+        self
+    }
+
+    pub fn with_garbage_timing(mut self, timing: GarbageTiming) -> Self {
+        self.garbage = GarbageQueue::new(timing);
+
+        self
+    }
+
+    pub fn with_handicap(mut self, handicap: Handicap) -> Self {
+        self.handicap = handicap;
+
+        self
+    }
+
+    pub fn handicap(&self) -> &Handicap {
+        &self.handicap
+    }
+
+    /// catch up with how far into margin time our round is
+    pub fn set_margin(&mut self, margin_time: &MarginTime, step: usize) {
+        self.margin_levels = margin_time.levels(step);
+        self.margin_attack = margin_time.attack_multiplier(step);
+    }
+
+    /// forget everything about our last round and start a new one
+    pub fn restart(&mut self) {
+        self.margin_levels = 0;
+        self.margin_attack = 1.;
+        self.attack_remainder = 0.;
+        self.incoming_remainder = 0.;
+        self.garbage.clear();
+        // starting our game empties our meter
+        self.shown_garbage = 0;
+        self.lines_cleared = 0;
+        self.locked_pieces = 0;
+        self.board_state = None;
+        self.simulation = Simulation::default();
+        if let Some(bot) = &mut self.bot {
+            bot.reset();
+        }
+        self.drop_input();
+
+        self.start_game();
+    }
+
     pub fn handle_opponent_lines(&mut self, lines: usize) {
-        self.pending_lines += lines;
+        self.garbage.push(scale_lines(
+            lines,
+            self.handicap.incoming,
+            &mut self.incoming_remainder,
+        ));
+        self.show_garbage();
+    }
+
+    fn show_garbage(&mut self) {
+        let pending = self.garbage.pending();
+        if pending != self.shown_garbage {
+            self.shown_garbage = pending;
+            self.tetris_tx.send_expect(TetrisIn::PendingRows(pending));
+        }
+    }
+
+    pub fn lines_cleared(&self) -> usize {
+        self.lines_cleared
+    }
+
+    pub fn locked_pieces(&self) -> usize {
+        self.locked_pieces
+    }
+
+    /// ask our game to hand over its board, it'll show up in `take_board_state`
+    pub fn request_board(&self) {
+        self.tetris_tx.send_expect(TetrisIn::TakeBoard);
+    }
+
+    pub fn take_board_state(&mut self) -> Option<BoardState> {
+        self.board_state.take()
+    }
+
+    pub fn put_board(&self, state: BoardState) {
+        self.tetris_tx.send_expect(TetrisIn::PutBoard(state));
     }
 }
 
 impl LocalPlayer for LocalAttackPlayer {
     fn level(&self) -> usize {
-        ATTACK_LEVEL
+        self.handicap.level + self.margin_levels
+    }
+
+    fn start_game(&self) {
+        let seed = rand::thread_rng().gen();
+        self.tetris_tx.send_expect(TetrisIn::Start(seed));
+
+        if self.handicap.starting_rows > 0 {
+            self.tetris_tx
+                .send_expect(TetrisIn::AddRows(self.handicap.starting_rows));
+        }
+    }
+
+    fn accepts(&mut self, input: UserInput) -> bool {
+        self.handicap.hold || input != UserInput::Hold
+    }
+
+    fn advance(&mut self, time: &Time) {
+        self.garbage.advance(time.delta_seconds());
+
+        if let Some(bot) = &mut self.bot {
+            bot.update(time);
+        }
     }
 
-    fn tick_timer(&mut self) -> &mut f32 {
-        &mut self.tick_timer
+    fn simulation(&mut self) -> &mut Simulation {
+        &mut self.simulation
     }
 
-    fn input_rx(&self) -> &Receiver<UserInput> {
+    fn input_rx(&self) -> &Receiver<TimedInput> {
         &self.input_rx
     }
 
@@ -185,3 +477,59 @@ impl LocalPlayer for LocalAttackPlayer {
         &self.tetris_rx
     }
 }
+
+/// scale some lines by a multiplier, carrying the fraction we couldn't send over to next time
+fn scale_lines(lines: usize, multiplier: f32, remainder: &mut f32) -> usize {
+    if lines == 0 {
+        return 0;
+    }
+
+    let scaled = lines as f32 * multiplier + *remainder;
+    let whole = scaled.floor().max(0.);
+    *remainder = scaled - whole;
+
+    whole as usize
+}
+
+/// outline where our hints would put a freshly spawned piece
+fn show_hint(hints: Option<Search>, state: &PieceState, tetris_tx: &Sender<TetrisIn>) {
+    if let Some(search) = hints {
+        let suggestion = search
+            .choose(state)
+            .placement
+            .map(|placement| placement.piece);
+
+        tetris_tx.send_expect(TetrisIn::Outline(suggestion));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_lines_pass_straight_through() {
+        let mut remainder = 0.;
+
+        assert_eq!(scale_lines(4, 1., &mut remainder), 4);
+        assert_eq!(scale_lines(3, 1.5, &mut remainder), 4);
+        assert!((remainder - 0.5).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn fractions_carry_over_to_our_next_attack() {
+        let mut remainder = 0.;
+
+        assert_eq!(scale_lines(1, 0.5, &mut remainder), 0);
+        assert_eq!(scale_lines(1, 0.5, &mut remainder), 1);
+        assert!(remainder.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn nothing_sent_keeps_our_remainder() {
+        let mut remainder = 0.5;
+
+        assert_eq!(scale_lines(0, 2., &mut remainder), 0);
+        assert!((remainder - 0.5).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,260 @@
+use amethyst::core::ecs::{Entity, Read, System, Write, WriteStorage};
+use amethyst::core::Time;
+use amethyst::ecs::SystemData;
+use amethyst::error::Error as AmethystError;
+use amethyst::prelude::*;
+use amethyst::renderer::palette::Srgba;
+use amethyst::ui::UiText;
+use crossbeam::channel;
+use crossbeam::channel::{Receiver, Sender};
+use log::info;
+
+use crate::events::{TetrisIn, TetrisOut, TimedInput};
+use crate::systems::control::handicap::label_above;
+use crate::systems::control::simulation::Simulation;
+use crate::systems::control::{LocalPlayer, MARGIN};
+use crate::systems::input_display::{beside, left_of};
+use crate::systems::input_system::InputSystemDesc;
+use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
+use crate::systems::tetris::{PixelColor, RENDERED_WIDTH};
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystemDesc};
+use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+
+/// One board shared by two players who take turns placing pieces
+pub struct RelayPlayer {
+    level: usize,
+    simulation: Simulation,
+    /// the index of the player who's currently in control
+    active: usize,
+    pieces_this_turn: usize,
+    pieces_per_turn: usize,
+    input_rxs: [Receiver<TimedInput>; 2],
+    tetris_tx: Sender<TetrisIn>,
+    tetris_rx: Receiver<TetrisOut>,
+}
+
+impl RelayPlayer {
+    pub fn new(
+        pieces_per_turn: usize,
+        input_rxs: [Receiver<TimedInput>; 2],
+        tetris_tx: Sender<TetrisIn>,
+        tetris_rx: Receiver<TetrisOut>,
+    ) -> RelayPlayer {
+        RelayPlayer {
+            level: 0,
+            simulation: Simulation::default(),
+            active: 0,
+            pieces_this_turn: 0,
+            pieces_per_turn: pieces_per_turn.max(1),
+            input_rxs,
+            tetris_tx,
+            tetris_rx,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// drop any input from the player who's waiting for their turn
+    pub fn drop_inactive_input(&self) {
+        let inactive = &self.input_rxs[1 - self.active];
+        while inactive.try_recv().is_ok() {}
+    }
+
+    /// return whether we lost
+    pub fn handle_events(&mut self) -> bool {
+        while let Ok(game_event) = self.tetris_rx.try_recv() {
+            match game_event {
+                TetrisOut::LockedPiece => {
+                    self.pieces_this_turn += 1;
+
+                    if self.pieces_this_turn >= self.pieces_per_turn {
+                        self.pieces_this_turn = 0;
+                        self.active = 1 - self.active;
+                        // whatever our last player still had coming isn't for our next one's piece
+                        self.simulation.clear_input();
+                    }
+                }
+                TetrisOut::Lose => return true,
+                _ => (),
+            }
+        }
+
+        false
+    }
+}
+
+impl LocalPlayer for RelayPlayer {
+    fn level(&self) -> usize {
+        self.level
+    }
+
+    fn simulation(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
+
+    fn input_rx(&self) -> &Receiver<TimedInput> {
+        &self.input_rxs[self.active]
+    }
+
+    fn tetris_tx(&self) -> &Sender<TetrisIn> {
+        &self.tetris_tx
+    }
+
+    fn tetris_rx(&self) -> &Receiver<TetrisOut> {
+        &self.tetris_rx
+    }
+}
+
+struct RelaySystem {
+    started: bool,
+    finished: bool,
+    player: RelayPlayer,
+    /// whose turn it is, above our board
+    turn: Entity,
+    shown_active: Option<usize>,
+}
+
+impl<'s> System<'s> for RelaySystem {
+    type SystemData = (
+        Read<'s, Time>,
+        WriteStorage<'s, UiText>,
+        Write<'s, GameOver>,
+    );
+
+    fn run(&mut self, (time, mut ui_text, mut game_over): Self::SystemData) {
+        if self.finished {
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+
+            self.player.start_game();
+        }
+
+        self.player.drop_inactive_input();
+        self.player.process_input(&time);
+
+        if self.player.handle_events() {
+            self.finished = true;
+
+            info!("Game over!");
+            game_over.summary = Some("Game over!".to_string());
+        }
+
+        let active = self.player.active();
+        if self.shown_active != Some(active) {
+            self.shown_active = Some(active);
+
+            if let Some(turn) = ui_text.get_mut(self.turn) {
+                turn.text = turn_text(active);
+                turn.color = text_color(player_color(active));
+            }
+        }
+    }
+}
+
+fn turn_text(active: usize) -> String {
+    format!("Player {}'s turn", active + 1)
+}
+
+fn text_color(color: PixelColor) -> [f32; 4] {
+    let (red, green, blue, alpha) = Into::<Srgba>::into(color).into_components();
+    [red, green, blue, alpha]
+}
+
+fn player_color(index: usize) -> PixelColor {
+    if index == 0 {
+        PixelColor::LightBlue
+    } else {
+        PixelColor::Orange
+    }
+}
+
+pub fn setup<'w>(
+    game_type: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
+    let pieces_per_turn = match game_type {
+        GameType::Relay(pieces_per_turn) => pieces_per_turn,
+        _ => unreachable!(),
+    };
+
+    let (one_input_tx, one_input_rx) = channel::unbounded();
+    let (two_input_tx, two_input_rx) = channel::unbounded();
+
+    let (player_in_tx, player_in_rx) = channel::unbounded();
+    let (player_out_tx, player_out_rx) = channel::unbounded();
+
+    let position = (MARGIN + RENDERED_WIDTH / 2., MARGIN);
+
+    game_data = game_data
+        .with_known_desc(InputSystemDesc {
+            input_txs: vec![one_input_tx, two_input_tx],
+            // we share a board, so each display goes on its player's side
+            overlays: vec![beside(position), left_of(position)],
+        })
+        .with_system_desc(
+            TetrisGameSystemDesc {
+                position,
+                in_rx: player_in_rx,
+                out_tx: player_out_tx,
+                config: TetrisRenderingConfig::default(),
+            },
+            "game_system_player",
+            &[],
+        )
+        .with_known_desc(RelaySystemDesc {
+            position,
+            player: RelayPlayer::new(
+                pieces_per_turn,
+                [one_input_rx, two_input_rx],
+                player_in_tx,
+                player_out_rx,
+            ),
+        });
+
+    Ok(game_data)
+}
+
+struct RelaySystemDesc {
+    /// the position of the board we're sharing
+    position: (f32, f32),
+    player: RelayPlayer,
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, RelaySystem> for RelaySystemDesc {
+    fn build(self, world: &mut World) -> RelaySystem {
+        <RelaySystem as System<'_>>::SystemData::setup(world);
+
+        // in the margin above our board, where handicaps go in our other modes
+        let turn = label_above(
+            "relay_turn",
+            self.position,
+            &turn_text(0),
+            text_color(player_color(0)),
+            world,
+        );
+
+        RelaySystem {
+            started: false,
+            finished: false,
+            player: self.player,
+            turn,
+            shown_active: None,
+        }
+    }
+}
+
+impl KnownSystem<'_> for RelaySystem {
+    fn name() -> KnownSystems {
+        KnownSystems::ControlSystem
+    }
+
+    fn dependencies() -> &'static [KnownSystems] {
+        &[]
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use crate::events::{TimedInput, UserInput};
+
+/// How often our games step, however fast or unevenly we render
+pub const STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// after a longer stall than this we skip ahead rather than play it all back at once
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/// Steps a player's game at a fixed rate, carrying any part of a frame we didn't step over to the next
+#[derive(Debug, Default)]
+pub struct Simulation {
+    /// how far we've stepped our game, the rest of our frame is waiting for its step to fill up
+    clock: Option<Duration>,
+    /// input that came in after the last step we ran
+    pending: Vec<TimedInput>,
+    /// time until our gravity next ticks
+    pub tick_timer: f32,
+}
+
+impl Simulation {
+    pub fn queue<I: IntoIterator<Item = TimedInput>>(&mut self, inputs: I) {
+        self.pending.extend(inputs);
+        self.pending.sort_by_key(|input| input.at);
+    }
+
+    /// the end of every step that's due by the end of our frame
+    pub fn steps(&mut self, frame_end: Duration, frame_length: Duration) -> Vec<Duration> {
+        let caught_up = |clock: Duration| {
+            frame_end
+                .checked_sub(clock)
+                .map_or(false, |behind| behind <= MAX_CATCH_UP)
+        };
+
+        let mut clock = match self.clock {
+            Some(clock) if caught_up(clock) => clock,
+            // we're just starting or we've stalled, so step from the start of this frame
+            _ => frame_end
+                .checked_sub(frame_length.min(MAX_CATCH_UP))
+                .unwrap_or_default(),
+        };
+
+        let mut steps = Vec::new();
+        while clock + STEP <= frame_end {
+            clock += STEP;
+            steps.push(clock);
+        }
+        self.clock = Some(clock);
+
+        steps
+    }
+
+    /// take our input that came in before a step ends, in the order it happened
+    pub fn inputs_before(&mut self, step_end: Duration) -> Vec<UserInput> {
+        let count = self
+            .pending
+            .iter()
+            .take_while(|input| input.at < step_end)
+            .count();
+
+        self.pending
+            .drain(..count)
+            .map(|input| input.input)
+            .collect()
+    }
+
+    /// forget input we haven't stepped yet, our clock keeps going
+    pub fn clear_input(&mut self) {
+        self.pending.clear();
+    }
+
+    /// forget our input and where we were, our next frame starts us again
+    pub fn clear(&mut self) {
+        self.clock = None;
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn partial_steps_carry_over() {
+        let mut simulation = Simulation::default();
+
+        // 25ms only fills one step, the rest waits for our next frame
+        assert_eq!(
+            simulation.steps(millis(1025), millis(25)),
+            vec![millis(1000) + STEP]
+        );
+        assert_eq!(
+            simulation.steps(millis(1035), millis(10)),
+            vec![millis(1000) + STEP * 2]
+        );
+        assert!(simulation.steps(millis(1040), millis(5)).is_empty());
+    }
+
+    #[test]
+    fn long_stalls_skip_ahead() {
+        let mut simulation = Simulation::default();
+        simulation.steps(millis(1000), millis(16));
+
+        let steps = simulation.steps(millis(3000), millis(2000));
+
+        assert_eq!(steps.len(), 15);
+        assert_eq!(steps[0], millis(2750) + STEP);
+    }
+
+    #[test]
+    fn inputs_stay_in_order_across_steps() {
+        let mut simulation = Simulation::default();
+        let steps = simulation.steps(millis(1040), millis(40));
+        simulation.queue(vec![
+            TimedInput {
+                input: UserInput::DropHard,
+                at: millis(1020),
+            },
+            TimedInput {
+                input: UserInput::Left,
+                at: millis(1005),
+            },
+            TimedInput {
+                input: UserInput::Right,
+                at: millis(1010),
+            },
+        ]);
+
+        assert_eq!(
+            simulation.inputs_before(steps[0]),
+            vec![UserInput::Left, UserInput::Right]
+        );
+        assert_eq!(
+            simulation.inputs_before(steps[1]),
+            vec![UserInput::DropHard]
+        );
+        assert!(simulation.inputs_before(steps[1]).is_empty());
+    }
+}
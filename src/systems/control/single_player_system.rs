@@ -1,30 +1,40 @@
-use amethyst::core::ecs::{Read, System};
+use amethyst::core::ecs::{Read, System, Write, WriteStorage};
 use amethyst::core::Time;
+use amethyst::ecs::{Entity, SystemData};
 use amethyst::error::Error as AmethystError;
-use amethyst::GameDataBuilder;
+use amethyst::prelude::*;
+use amethyst::ui::{Anchor, UiText};
 use crossbeam::channel;
 
-use crate::systems::control::{LocalPlayer, SinglePlayer, MARGIN};
+use crate::states::camera_scale;
+use crate::systems::control::{Hints, LocalPlayer, SinglePlayer, MARGIN};
+use crate::systems::input_display::beside;
 use crate::systems::input_system::InputSystemDesc;
 use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
-use crate::systems::tetris::RENDERED_WIDTH;
-use crate::systems::utils::{KnownSystem, WithKnownSystem, WithKnownSystemDesc};
+use crate::systems::tetris::{PIXEL_DIMENSION, RENDERED_WIDTH, VISIBLE_HEIGHT};
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystemDesc};
 use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+
+// where our board sits, centered in our window
+const POSITION: (f32, f32) = (MARGIN + RENDERED_WIDTH / 2., MARGIN);
 
 struct SinglePlayerSystem {
     started: bool,
     player: SinglePlayer,
+    /// our finesse so far, above our board
+    finesse_text: Entity,
 }
 
-// TODO create an event system for the entire game, we can reuse it for different things
-// start game: send start events
-// won
-// lost
-// etc
 impl<'s> System<'s> for SinglePlayerSystem {
-    type SystemData = Read<'s, Time>;
+    type SystemData = (
+        Read<'s, Time>,
+        WriteStorage<'s, UiText>,
+        Write<'s, GameOver>,
+    );
 
-    fn run(&mut self, time: Self::SystemData) {
+    fn run(&mut self, (time, mut ui_text, mut game_over): Self::SystemData) {
         if !self.started {
             self.started = true;
 
@@ -33,42 +43,85 @@ impl<'s> System<'s> for SinglePlayerSystem {
 
         self.player.process_input(&time);
 
-        self.player.handle_events();
+        if self.player.handle_events() {
+            game_over.summary = Some(format!("Game over!  {}", self.player.finesse_summary()));
+        }
+
+        if let Some(text) = ui_text.get_mut(self.finesse_text) {
+            text.text = self.player.finesse_summary();
+        }
     }
 }
 
-pub fn setup<'a, 'b>(
-    _: GameType,
-    mut game_data: GameDataBuilder<'a, 'b>,
-) -> Result<GameDataBuilder<'a, 'b>, AmethystError> {
+pub fn setup<'w>(
+    game_type: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
     let (input_tx, input_rx) = channel::unbounded();
 
     let (player_in_tx, player_in_rx) = channel::unbounded();
     let (player_out_tx, player_out_rx) = channel::unbounded();
 
+    let mut player = SinglePlayer::new(input_rx, player_in_tx, player_out_rx);
+    if let GameType::Trainer = game_type {
+        player = player.with_trainer();
+    }
+
     game_data = game_data
         .with_known_desc(InputSystemDesc {
-            one_input_tx: input_tx,
-            two_input_tx: None,
+            input_txs: vec![input_tx],
+            overlays: vec![beside(POSITION)],
         })
         .with_system_desc(
             TetrisGameSystemDesc {
-                position: (MARGIN + RENDERED_WIDTH / 2., MARGIN),
+                position: POSITION,
                 in_rx: player_in_rx,
                 out_tx: player_out_tx,
                 config: TetrisRenderingConfig::default(),
             },
             "game_system_player",
-            &[KnownSystems::SpriteLoader.into()],
+            &[],
         )
-        .with_known(SinglePlayerSystem {
-            started: false,
-            player: SinglePlayer::new(input_rx, player_in_tx, player_out_rx),
-        });
+        .with_known_desc(SinglePlayerSystemDesc { player });
 
     Ok(game_data)
 }
 
+struct SinglePlayerSystemDesc {
+    player: SinglePlayer,
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, SinglePlayerSystem> for SinglePlayerSystemDesc {
+    fn build(self, world: &mut World) -> SinglePlayerSystem {
+        <SinglePlayerSystem as System<'_>>::SystemData::setup(world);
+
+        let config = UiTextConfig {
+            id: "finesse",
+            width: 400.,
+            height: 20.,
+            anchor: Anchor::BottomLeft,
+            ..UiTextConfig::default()
+        };
+        let scale = camera_scale(world);
+        let (x, y) = POSITION;
+        let center_x = (x + RENDERED_WIDTH / 2.) / scale;
+        let center_y = (y + PIXEL_DIMENSION * VISIBLE_HEIGHT as f32 + MARGIN / 2.) / scale;
+
+        let finesse_text = UiTextConfig {
+            x: center_x - config.width / 2.,
+            y: center_y - config.height / 2.,
+            ..config
+        }
+        .build("", world);
+
+        SinglePlayerSystem {
+            started: false,
+            player: self.player.with_hints(Hints::fetch(world)),
+            finesse_text,
+        }
+    }
+}
+
 impl KnownSystem<'_> for SinglePlayerSystem {
     fn name() -> KnownSystems {
         KnownSystems::ControlSystem
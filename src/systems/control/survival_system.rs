@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use amethyst::core::ecs::{Read, System, Write};
+use amethyst::core::Time;
+use amethyst::error::Error as AmethystError;
+use amethyst::utils::application_root_dir;
+use crossbeam::channel;
+use log::info;
+
+use crate::systems::control::leaderboard::Leaderboard;
+use crate::systems::control::{LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::systems::input_display::beside;
+use crate::systems::input_system::InputSystemDesc;
+use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
+use crate::systems::tetris::RENDERED_WIDTH;
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystem, WithKnownSystemDesc};
+use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+
+// seconds between garbage rows at the start of a run
+const INITIAL_GARBAGE_INTERVAL: f32 = 8.;
+// the fastest our garbage will ever come in
+const MINIMUM_GARBAGE_INTERVAL: f32 = 1.;
+// how much faster the garbage comes for every second survived
+const GARBAGE_ACCELERATION: f32 = 0.02;
+const GARBAGE_ROWS: usize = 1;
+
+/// Survive as long as possible against garbage that never stops coming
+struct SurvivalSystem {
+    started: bool,
+    finished: bool,
+    survival_time: f32,
+    garbage_timer: f32,
+    player: LocalAttackPlayer,
+    leaderboard_path: PathBuf,
+}
+
+impl SurvivalSystem {
+    fn finish(&mut self, game_over: &mut GameOver) {
+        self.finished = true;
+
+        let lines_cleared = self.player.lines_cleared();
+        let mut summary = format!(
+            "Survived {:.2} seconds and cleared {} lines",
+            self.survival_time, lines_cleared
+        );
+        info!("{}", summary);
+
+        let mut leaderboard = Leaderboard::open(&self.leaderboard_path);
+        let rank = leaderboard.record(self.survival_time, lines_cleared);
+        if let Some(rank) = rank {
+            info!("New leaderboard entry at #{}", rank);
+            summary = format!("{}, #{} on the leaderboard!", summary, rank);
+        }
+        game_over.summary = Some(summary);
+        leaderboard.save(&self.leaderboard_path);
+
+        // our results list our whole leaderboard, pointing out our new run
+        game_over.details = leaderboard
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                format!(
+                    "{}#{}  {:.2}s  {} lines  {}",
+                    if rank == Some(i + 1) { "> " } else { "" },
+                    i + 1,
+                    entry.survival_time,
+                    entry.lines_cleared,
+                    entry.date
+                )
+            })
+            .collect();
+    }
+}
+
+fn garbage_interval(survival_time: f32) -> f32 {
+    (INITIAL_GARBAGE_INTERVAL - survival_time * GARBAGE_ACCELERATION).max(MINIMUM_GARBAGE_INTERVAL)
+}
+
+impl<'s> System<'s> for SurvivalSystem {
+    type SystemData = (Read<'s, Time>, Write<'s, GameOver>);
+
+    fn run(&mut self, (time, mut game_over): Self::SystemData) {
+        if self.finished {
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+            self.garbage_timer = garbage_interval(0.);
+
+            self.player.start_game();
+        }
+
+        self.player.process_input(&time);
+
+        self.survival_time += time.delta_seconds();
+
+        // our garbage goes through the same path as an opponent's attack
+        self.garbage_timer -= time.delta_seconds();
+        if self.garbage_timer <= 0. {
+            self.player.handle_opponent_lines(GARBAGE_ROWS);
+            self.garbage_timer += garbage_interval(self.survival_time);
+        }
+
+        let (_, lost) = self.player.handle_events(|_| ());
+
+        if lost {
+            self.finish(&mut game_over);
+        }
+    }
+}
+
+pub fn setup<'w>(
+    _: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
+    let leaderboard_path = application_root_dir()?
+        .join("resources")
+        .join("leaderboard.ron");
+
+    let (input_tx, input_rx) = channel::unbounded();
+
+    let (player_in_tx, player_in_rx) = channel::unbounded();
+    let (player_out_tx, player_out_rx) = channel::unbounded();
+
+    game_data = game_data
+        .with_known_desc(InputSystemDesc {
+            input_txs: vec![input_tx],
+            overlays: vec![beside((MARGIN + RENDERED_WIDTH / 2., MARGIN))],
+        })
+        .with_system_desc(
+            TetrisGameSystemDesc {
+                position: (MARGIN + RENDERED_WIDTH / 2., MARGIN),
+                in_rx: player_in_rx,
+                out_tx: player_out_tx,
+                config: TetrisRenderingConfig::default(),
+            },
+            "game_system_player",
+            &[],
+        )
+        .with_known(SurvivalSystem {
+            started: false,
+            finished: false,
+            survival_time: 0.,
+            garbage_timer: 0.,
+            player: LocalAttackPlayer::new(input_rx, player_in_tx, player_out_rx),
+            leaderboard_path,
+        });
+
+    Ok(game_data)
+}
+
+impl KnownSystem<'_> for SurvivalSystem {
+    fn name() -> KnownSystems {
+        KnownSystems::ControlSystem
+    }
+
+    fn dependencies() -> &'static [KnownSystems] {
+        &[]
+    }
+}
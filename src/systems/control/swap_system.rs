@@ -0,0 +1,323 @@
+use amethyst::core::ecs::{Entity, Read, System, Write, WriteStorage};
+use amethyst::core::Time;
+use amethyst::ecs::SystemData;
+use amethyst::error::Error as AmethystError;
+use amethyst::prelude::*;
+use amethyst::ui::{Anchor, UiText};
+use crossbeam::channel;
+
+use crate::systems::control::handicap::Handicaps;
+use crate::systems::control::versus_match::{player_names, VersusMatch};
+use crate::systems::control::{Hints, LocalAttackPlayer, LocalPlayer, MARGIN};
+use crate::systems::input_display::beside;
+use crate::systems::input_system::InputSystemDesc;
+use crate::systems::tetris::tetris_system::{TetrisGameSystemDesc, TetrisRenderingConfig};
+use crate::systems::tetris::{BoardState, RENDERED_WIDTH};
+use crate::systems::ui_text::UiTextConfig;
+use crate::systems::utils::{KnownSystem, SystemsBuilder, WithKnownSystemDesc};
+use crate::systems::{GameType, KnownSystems};
+use crate::GameOver;
+
+// how many seconds of warning we give before swapping on a timer
+const WARNING_SECONDS: f32 = 3.;
+// how many pieces of warning we give before swapping on locks
+const WARNING_LOCKS: usize = 2;
+
+// player one is on the right, like their hands on our keyboard
+const ONE_POSITION: (f32, f32) = (RENDERED_WIDTH + MARGIN * 2., MARGIN);
+const TWO_POSITION: (f32, f32) = (MARGIN, MARGIN);
+
+/// When our players trade boards
+#[derive(Copy, Clone, Debug)]
+pub enum SwapTrigger {
+    Seconds(f32),
+    Locks(usize),
+}
+
+/// A versus game where the players trade boards every so often
+struct SwapSystem {
+    started: bool,
+    trigger: SwapTrigger,
+    /// seconds since our last swap
+    swap_timer: f32,
+    /// how many pieces were locked by each player at our last swap
+    swap_locks: [usize; 2],
+    swapping: bool,
+    /// the boards we've been handed while swapping
+    swapped_boards: [Option<BoardState>; 2],
+    one: LocalAttackPlayer,
+    two: LocalAttackPlayer,
+    countdown: Entity,
+    versus: VersusMatch,
+}
+
+impl SwapSystem {
+    fn locks_since_swap(&self) -> usize {
+        (self.one.locked_pieces() - self.swap_locks[0])
+            .max(self.two.locked_pieces() - self.swap_locks[1])
+    }
+
+    fn start_swap(&mut self) {
+        self.swapping = true;
+
+        self.one.request_board();
+        self.two.request_board();
+    }
+
+    fn finish_swap(&mut self) {
+        if self.swapped_boards[0].is_none() {
+            self.swapped_boards[0] = self.one.take_board_state();
+        }
+        if self.swapped_boards[1].is_none() {
+            self.swapped_boards[1] = self.two.take_board_state();
+        }
+
+        // wait until we have both boards
+        if let [Some(_), Some(_)] = self.swapped_boards {
+            let [one_state, two_state] = std::mem::take(&mut self.swapped_boards);
+
+            self.one.put_board(two_state.expect("We just checked this"));
+            self.two.put_board(one_state.expect("We just checked this"));
+
+            self.swapping = false;
+            self.swap_timer = 0.;
+            self.swap_locks = [self.one.locked_pieces(), self.two.locked_pieces()];
+        }
+    }
+
+    fn restart(&mut self) {
+        self.swap_timer = 0.;
+        self.swap_locks = [0, 0];
+        self.swapping = false;
+        self.swapped_boards = [None, None];
+
+        self.one.restart();
+        self.two.restart();
+    }
+
+    fn countdown_text(&self) -> String {
+        if !self.versus.playing() {
+            return String::new();
+        }
+
+        if self.swapping {
+            return "Swap!".to_string();
+        }
+
+        match self.trigger {
+            SwapTrigger::Seconds(seconds) => {
+                let remaining = seconds - self.swap_timer;
+                if remaining <= WARNING_SECONDS {
+                    format!("Swap in {}", remaining.ceil() as usize)
+                } else {
+                    String::new()
+                }
+            }
+            SwapTrigger::Locks(locks) => {
+                let remaining = locks.saturating_sub(self.locks_since_swap());
+                if remaining <= WARNING_LOCKS {
+                    format!("Swap in {} pieces", remaining)
+                } else {
+                    String::new()
+                }
+            }
+        }
+    }
+}
+
+impl<'s> System<'s> for SwapSystem {
+    type SystemData = (
+        Read<'s, Time>,
+        WriteStorage<'s, UiText>,
+        Write<'s, GameOver>,
+    );
+
+    fn run(&mut self, (time, mut ui_text, mut game_over): Self::SystemData) {
+        if !self.started {
+            self.started = true;
+
+            self.one.start_game();
+            self.two.start_game();
+        }
+
+        let margin_step = self.versus.margin_step();
+        self.one.set_margin(self.versus.margin_time(), margin_step);
+        self.two.set_margin(self.versus.margin_time(), margin_step);
+
+        let playing = self.versus.playing();
+        if playing {
+            self.one.process_input(&time);
+            self.two.process_input(&time);
+        } else {
+            self.one.drop_input();
+            self.two.drop_input();
+        }
+
+        // read the output and see if anything interesting happened in our game
+        let (one_lines, one_lost) = self.one.handle_events(|_| ());
+        let (two_lines, two_lost) = self.two.handle_events(|_| ());
+
+        if playing {
+            // update our lines from our opponents
+            self.one.handle_opponent_lines(two_lines);
+            self.two.handle_opponent_lines(one_lines);
+
+            match (one_lost, two_lost) {
+                (false, false) => {}
+                (true, true) => self.versus.round_over(None),
+                (false, true) => self.versus.round_over(Some(0)),
+                (true, false) => self.versus.round_over(Some(1)),
+            }
+        }
+
+        if self.versus.playing() {
+            if self.swapping {
+                self.finish_swap();
+            } else {
+                self.swap_timer += time.delta_seconds();
+
+                let should_swap = match self.trigger {
+                    SwapTrigger::Seconds(seconds) => self.swap_timer >= seconds,
+                    SwapTrigger::Locks(locks) => self.locks_since_swap() >= locks,
+                };
+
+                if should_swap {
+                    self.start_swap();
+                }
+            }
+        }
+
+        if self
+            .versus
+            .update(time.delta_seconds(), &mut ui_text, &mut game_over)
+        {
+            self.restart();
+        }
+
+        if let Some(text) = ui_text.get_mut(self.countdown) {
+            text.text = self.countdown_text();
+        }
+    }
+}
+
+pub fn setup<'w>(
+    game_type: GameType,
+    mut game_data: SystemsBuilder<'w>,
+) -> Result<SystemsBuilder<'w>, AmethystError> {
+    let trigger = match game_type {
+        GameType::Swap(trigger) => trigger,
+        _ => unreachable!(),
+    };
+
+    let (one_input_tx, one_input_rx) = channel::unbounded();
+    let (two_input_tx, two_input_rx) = channel::unbounded();
+
+    let (one_in_tx, one_in_rx) = channel::unbounded();
+    let (one_out_tx, one_out_rx) = channel::unbounded();
+
+    let (two_in_tx, two_in_rx) = channel::unbounded();
+    let (two_out_tx, two_out_rx) = channel::unbounded();
+
+    // our handicaps stay with our players, whichever board they're playing on
+    let handicaps = Handicaps::load()?;
+    let (one_handicap, two_handicap) = (handicaps.player(0), handicaps.player(1));
+
+    game_data = game_data
+        .with_known_desc(InputSystemDesc {
+            input_txs: vec![one_input_tx, two_input_tx],
+            overlays: vec![beside(ONE_POSITION), beside(TWO_POSITION)],
+        })
+        .with_system_desc(
+            TetrisGameSystemDesc {
+                position: ONE_POSITION,
+                in_rx: one_in_rx,
+                out_tx: one_out_tx,
+                config: TetrisRenderingConfig {
+                    show_ghost: one_handicap.ghost,
+                    show_hold: one_handicap.hold,
+                    ..TetrisRenderingConfig::default()
+                },
+            },
+            "game_system_player_one",
+            &[],
+        )
+        .with_system_desc(
+            TetrisGameSystemDesc {
+                position: TWO_POSITION,
+                in_rx: two_in_rx,
+                out_tx: two_out_tx,
+                config: TetrisRenderingConfig {
+                    show_ghost: two_handicap.ghost,
+                    show_hold: two_handicap.hold,
+                    ..TetrisRenderingConfig::default()
+                },
+            },
+            "game_system_player_two",
+            &[],
+        )
+        .with_known_desc(SwapSystemDesc {
+            trigger,
+            one: LocalAttackPlayer::new(one_input_rx, one_in_tx, one_out_rx)
+                .with_handicap(one_handicap),
+            two: LocalAttackPlayer::new(two_input_rx, two_in_tx, two_out_rx)
+                .with_handicap(two_handicap),
+        });
+
+    Ok(game_data)
+}
+
+struct SwapSystemDesc {
+    trigger: SwapTrigger,
+    one: LocalAttackPlayer,
+    two: LocalAttackPlayer,
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, SwapSystem> for SwapSystemDesc {
+    fn build(self, world: &mut World) -> SwapSystem {
+        <SwapSystem as System<'_>>::SystemData::setup(world);
+
+        let countdown = UiTextConfig {
+            id: "swap_countdown",
+            y: -MARGIN,
+            anchor: Anchor::TopMiddle,
+            color: [1., 0., 0., 1.],
+            ..UiTextConfig::default()
+        }
+        .build("", world);
+
+        self.one.handicap().show_label(ONE_POSITION, world);
+        self.two.handicap().show_label(TWO_POSITION, world);
+
+        let versus = VersusMatch::new(player_names(2), world);
+        let hints = Hints::fetch(world);
+
+        SwapSystem {
+            started: false,
+            trigger: self.trigger,
+            swap_timer: 0.,
+            swap_locks: [0, 0],
+            swapping: false,
+            swapped_boards: [None, None],
+            one: self
+                .one
+                .with_garbage_timing(versus.garbage_timing())
+                .with_hints(hints),
+            two: self
+                .two
+                .with_garbage_timing(versus.garbage_timing())
+                .with_hints(hints),
+            countdown,
+            versus,
+        }
+    }
+}
+
+impl KnownSystem<'_> for SwapSystem {
+    fn name() -> KnownSystems {
+        KnownSystems::ControlSystem
+    }
+
+    fn dependencies() -> &'static [KnownSystems] {
+        &[]
+    }
+}
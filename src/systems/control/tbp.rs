@@ -0,0 +1,565 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+
+use amethyst::core::Time;
+use crossbeam::channel::{self, Receiver, Sender};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{TimedInput, UserInput};
+use crate::systems::control::bot::{place, Bot, PlannedInputs};
+use crate::systems::tetris::{
+    Board, BoardPixel, Piece, PieceState, PixelColor, Placement, Tetrimino,
+};
+
+// how long we take to make the inputs for each piece the bot places
+const PIECE_SECONDS: f32 = 0.25;
+
+/// Messages we send to our bot, see the Tetris Bot Protocol
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    Rules {},
+    Start {
+        hold: Option<Tetrimino>,
+        queue: Vec<Tetrimino>,
+        combo: u32,
+        back_to_back: bool,
+        board: Vec<Vec<Option<char>>>,
+    },
+    Suggest,
+    NewPiece {
+        piece: Tetrimino,
+    },
+    Play {
+        #[serde(rename = "move")]
+        placement: Move,
+    },
+    Stop,
+    Quit,
+}
+
+/// Messages our bot sends back
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct Move {
+    location: Location,
+    spin: String,
+}
+
+/// Where a piece ends up, `x` and `y` are its rotation center
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct Location {
+    #[serde(rename = "type")]
+    tetrimino: Tetrimino,
+    orientation: TbpOrientation,
+    x: isize,
+    y: isize,
+}
+
+impl Location {
+    /// the board pixels our piece covers
+    fn pixels(&self) -> HashSet<(isize, isize)> {
+        let north: [(isize, isize); 4] = match self.tetrimino {
+            Tetrimino::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            Tetrimino::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Tetrimino::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Tetrimino::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Tetrimino::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            Tetrimino::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Tetrimino::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+
+        north
+            .iter()
+            .map(|&(x, y)| match self.orientation {
+                TbpOrientation::North => (x, y),
+                TbpOrientation::East => (y, -x),
+                TbpOrientation::South => (-x, -y),
+                TbpOrientation::West => (-y, x),
+            })
+            .map(|(x, y)| (self.x + x, self.y + y))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+enum TbpOrientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+/// Where we are in getting our bot ready to play
+#[derive(Debug, PartialEq)]
+enum Handshake {
+    WaitingForInfo,
+    WaitingForRules,
+    Ready,
+}
+
+/// What our bot thinks our game looks like, it hears about every piece we play
+struct BotGame {
+    board: Board,
+    hold: Option<Tetrimino>,
+    queue: VecDeque<Tetrimino>,
+}
+
+impl BotGame {
+    fn start(state: &PieceState) -> BotGame {
+        BotGame {
+            board: state.board.clone(),
+            hold: state.held,
+            queue: vec![state.piece.tetrimino, state.next].into(),
+        }
+    }
+
+    fn message(&self) -> FrontendMessage {
+        FrontendMessage::Start {
+            hold: self.hold,
+            queue: self.queue.iter().copied().collect(),
+            combo: 0,
+            back_to_back: false,
+            board: tbp_board(&self.board),
+        }
+    }
+
+    /// the pieces our bot hasn't seen yet, if the rest of our game is still what it thinks it is
+    fn new_pieces(&self, state: &PieceState) -> Option<Vec<Tetrimino>> {
+        let visible = [state.piece.tetrimino, state.next];
+        let seen = self.queue.len();
+
+        if self.board == state.board
+            && self.hold == state.held
+            && seen <= visible.len()
+            && self.queue.iter().eq(&visible[..seen])
+        {
+            Some(visible[seen..].to_vec())
+        } else {
+            None
+        }
+    }
+
+    /// lock our piece the way our bot will, holding first if it isn't the piece at the front of our queue
+    fn play(&mut self, piece: &Piece) {
+        let current = self.queue.pop_front();
+        if current != Some(piece.tetrimino) {
+            // with nothing held we play the piece after the one we put away
+            if self.hold.is_none() {
+                self.queue.pop_front();
+            }
+            self.hold = current;
+        }
+
+        self.board = place(&self.board, piece).0;
+    }
+}
+
+/// Hands a player slot to an external bot speaking the Tetris Bot Protocol over stdio
+pub struct TbpBot {
+    process: Child,
+    /// our messages' lines, written out in the background so a slow bot never holds up our game
+    outgoing: Sender<String>,
+    messages: Receiver<BotMessage>,
+    handshake: Handshake,
+    /// the piece we still need a suggestion for
+    waiting: Option<PieceState>,
+    /// the move our bot picked for the piece we're holding to get out
+    holding: Option<Move>,
+    /// the game our bot has going, we only start another when garbage changes our board
+    game: Option<BotGame>,
+    input_tx: Sender<TimedInput>,
+    plan: PlannedInputs,
+}
+
+impl TbpBot {
+    /// start our bot's process from a command line like `cold-clear --tbp`
+    pub fn spawn(command: &str, input_tx: Sender<TimedInput>) -> io::Result<TbpBot> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty bot command"))?;
+
+        let mut process = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = process.stdin.take().expect("We asked for a piped stdin");
+        let stdout = process.stdout.take().expect("We asked for a piped stdout");
+
+        // write to our bot in the background too, it might not read its input as fast as we send
+        let (outgoing, lines) = channel::unbounded();
+        thread::spawn(move || write_lines(stdin, lines));
+
+        // read our bot's messages in the background so we never wait on it
+        let (messages_tx, messages) = channel::unbounded();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+
+                match serde_json::from_str::<BotMessage>(&line) {
+                    Ok(message) => {
+                        if messages_tx.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Couldn't read bot message {}: {}", line, e),
+                }
+            }
+        });
+
+        Ok(TbpBot {
+            process,
+            outgoing,
+            messages,
+            handshake: Handshake::WaitingForInfo,
+            waiting: None,
+            holding: None,
+            game: None,
+            input_tx,
+            plan: PlannedInputs::default(),
+        })
+    }
+
+    fn send(&mut self, message: &FrontendMessage) {
+        let line = serde_json::to_string(message).expect("Our messages always serialize");
+
+        if self.outgoing.send(line).is_err() {
+            warn!(
+                "Couldn't send {:?} to our bot, it stopped listening",
+                message
+            );
+        }
+    }
+
+    /// tell our bot about our new pieces, restarting its game if garbage changed our board
+    fn request_suggestion(&mut self) {
+        let state = match self.waiting.take() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let new_pieces = self.game.as_ref().and_then(|game| game.new_pieces(&state));
+        match new_pieces {
+            Some(pieces) => {
+                for piece in pieces {
+                    self.send(&FrontendMessage::NewPiece { piece });
+                    if let Some(game) = &mut self.game {
+                        game.queue.push_back(piece);
+                    }
+                }
+            }
+            None => {
+                if self.game.is_some() {
+                    self.send(&FrontendMessage::Stop);
+                }
+
+                let game = BotGame::start(&state);
+                self.send(&game.message());
+                self.game = Some(game);
+            }
+        }
+
+        self.send(&FrontendMessage::Suggest);
+        self.waiting = Some(state);
+    }
+
+    /// tell our bot we're playing its move, so it can plan our next piece from there
+    fn play(&mut self, suggested: Move, piece: &Piece) {
+        self.send(&FrontendMessage::Play {
+            placement: suggested,
+        });
+
+        if let Some(game) = &mut self.game {
+            game.play(piece);
+        }
+    }
+
+    /// the first suggested move we can make from where our piece is
+    fn choose(&mut self, state: &PieceState, moves: Vec<Move>) -> Vec<UserInput> {
+        for suggested in moves {
+            if suggested.location.tetrimino == state.piece.tetrimino {
+                if let Some(placement) = reach(state, &suggested.location) {
+                    self.play(suggested, &placement.piece);
+                    return placement.inputs;
+                }
+            } else if state.can_hold {
+                // we play our bot's move once our held piece is out
+                self.holding = Some(suggested);
+                return vec![UserInput::Hold];
+            }
+        }
+
+        warn!("Our bot didn't suggest anything we can reach, dropping our piece");
+        vec![UserInput::DropHard]
+    }
+}
+
+impl Bot for TbpBot {
+    fn plan(&mut self, state: &PieceState) {
+        self.plan.clear();
+
+        if let Some(suggested) = self.holding.take() {
+            if let Some(placement) = reach(state, &suggested.location) {
+                self.play(suggested, &placement.piece);
+                self.plan.set(placement.inputs, PIECE_SECONDS);
+                return;
+            }
+        }
+
+        self.waiting = Some(state.clone());
+
+        if self.handshake == Handshake::Ready {
+            self.request_suggestion();
+        }
+    }
+
+    fn update(&mut self, time: &Time) {
+        while let Ok(message) = self.messages.try_recv() {
+            match message {
+                BotMessage::Info {
+                    name,
+                    version,
+                    author,
+                } => {
+                    info!("Playing with {} {} by {}", name, version, author);
+
+                    self.send(&FrontendMessage::Rules {});
+                    self.handshake = Handshake::WaitingForRules;
+                }
+                BotMessage::Ready => {
+                    self.handshake = Handshake::Ready;
+                    self.request_suggestion();
+                }
+                BotMessage::Error { reason } => warn!("Our bot refused to play: {}", reason),
+                BotMessage::Suggestion { moves } => {
+                    if let Some(state) = self.waiting.take() {
+                        let inputs = self.choose(&state, moves);
+                        self.plan.set(inputs, PIECE_SECONDS);
+                    }
+                }
+            }
+        }
+
+        self.plan.update(time, &self.input_tx);
+    }
+
+    fn reset(&mut self) {
+        self.plan.clear();
+        self.waiting = None;
+        self.holding = None;
+
+        if self.game.take().is_some() {
+            self.send(&FrontendMessage::Stop);
+        }
+    }
+}
+
+impl Drop for TbpBot {
+    fn drop(&mut self) {
+        // our writer may not get this out before we kill our bot below, which ends its game just the same
+        self.send(&FrontendMessage::Quit);
+
+        // make sure we don't leave our bot running if it ignores us
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// write each of our lines to our bot until it stops reading or we stop sending
+fn write_lines(mut stdin: ChildStdin, lines: Receiver<String>) {
+    for line in lines {
+        if let Err(e) = writeln!(stdin, "{}", line) {
+            warn!("Couldn't write to our bot: {}", e);
+            break;
+        }
+    }
+}
+
+/// the placement of our piece that covers the same pixels as our bot's location
+fn reach(state: &PieceState, location: &Location) -> Option<Placement> {
+    let pixels = location.pixels();
+
+    state
+        .board
+        .placements(&state.piece)
+        .into_iter()
+        .find(|placement| placement.piece.board_pixels().collect::<HashSet<_>>() == pixels)
+}
+
+/// our board as rows from the bottom up, each cell is the piece that filled it or garbage
+fn tbp_board(board: &Board) -> Vec<Vec<Option<char>>> {
+    (0..board.height() as isize)
+        .map(|y| {
+            (0..board.width() as isize)
+                .map(|x| match board.get(x, y) {
+                    BoardPixel::Filled(color) => Some(tbp_cell(color)),
+                    BoardPixel::Empty => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn tbp_cell(color: PixelColor) -> char {
+    match color {
+        PixelColor::LightBlue => 'I',
+        PixelColor::DarkBlue => 'J',
+        PixelColor::Orange => 'L',
+        PixelColor::Yellow => 'O',
+        PixelColor::Green => 'S',
+        PixelColor::Red => 'Z',
+        PixelColor::Magenta => 'T',
+        PixelColor::Gray => 'G',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(tetrimino: Tetrimino, orientation: TbpOrientation, x: isize, y: isize) -> Location {
+        Location {
+            tetrimino,
+            orientation,
+            x,
+            y,
+        }
+    }
+
+    fn cells(cells: &[(isize, isize)]) -> HashSet<(isize, isize)> {
+        cells.iter().copied().collect()
+    }
+
+    fn state(tetrimino: Tetrimino, next: Tetrimino) -> PieceState {
+        PieceState {
+            board: Board::new(),
+            piece: Piece::spawn(tetrimino, 3),
+            next,
+            held: None,
+            can_hold: true,
+        }
+    }
+
+    #[test]
+    fn locations_are_centered_like_the_spec() {
+        let t = location(Tetrimino::T, TbpOrientation::North, 4, 0);
+        assert_eq!(t.pixels(), cells(&[(3, 0), (4, 0), (5, 0), (4, 1)]));
+
+        let i = location(Tetrimino::I, TbpOrientation::North, 4, 0);
+        assert_eq!(i.pixels(), cells(&[(3, 0), (4, 0), (5, 0), (6, 0)]));
+
+        let o = location(Tetrimino::O, TbpOrientation::North, 4, 0);
+        assert_eq!(o.pixels(), cells(&[(4, 0), (5, 0), (4, 1), (5, 1)]));
+    }
+
+    #[test]
+    fn locations_rotate_clockwise_about_their_center() {
+        let t = |orientation| location(Tetrimino::T, orientation, 4, 1).pixels();
+        assert_eq!(
+            t(TbpOrientation::East),
+            cells(&[(4, 2), (4, 1), (4, 0), (5, 1)])
+        );
+        assert_eq!(
+            t(TbpOrientation::South),
+            cells(&[(3, 1), (4, 1), (5, 1), (4, 0)])
+        );
+        assert_eq!(
+            t(TbpOrientation::West),
+            cells(&[(4, 2), (4, 1), (4, 0), (3, 1)])
+        );
+
+        let i = location(Tetrimino::I, TbpOrientation::East, 4, 2);
+        assert_eq!(i.pixels(), cells(&[(4, 3), (4, 2), (4, 1), (4, 0)]));
+
+        let o = location(Tetrimino::O, TbpOrientation::South, 5, 1);
+        assert_eq!(o.pixels(), cells(&[(4, 0), (5, 0), (4, 1), (5, 1)]));
+    }
+
+    #[test]
+    fn locations_match_our_placements() {
+        let state = state(Tetrimino::T, Tetrimino::I);
+
+        let flat = location(Tetrimino::T, TbpOrientation::South, 4, 1);
+        let against_wall = location(Tetrimino::T, TbpOrientation::East, 0, 1);
+        assert!(reach(&state, &flat).is_some());
+        assert!(reach(&state, &against_wall).is_some());
+
+        // floating above our floor isn't somewhere we can lock
+        let floating = location(Tetrimino::T, TbpOrientation::South, 4, 2);
+        assert!(reach(&state, &floating).is_none());
+    }
+
+    #[test]
+    fn boards_go_from_the_bottom_up() {
+        let mut board = Board::new();
+        board.set(0, 0, BoardPixel::Filled(Tetrimino::T.color()));
+        board.set(9, 1, BoardPixel::Filled(PixelColor::Gray));
+
+        let rows = tbp_board(&board);
+        assert_eq!(rows.len(), 40);
+        assert!(rows.iter().all(|row| row.len() == 10));
+        assert_eq!(rows[0][0], Some('T'));
+        assert_eq!(rows[0][1], None);
+        assert_eq!(rows[1][9], Some('G'));
+        assert!(rows[2..].iter().flatten().all(Option::is_none));
+    }
+
+    #[test]
+    fn our_bot_hears_about_each_new_piece() {
+        let first = state(Tetrimino::T, Tetrimino::I);
+        let mut game = BotGame::start(&first);
+        let placement = reach(&first, &location(Tetrimino::T, TbpOrientation::South, 4, 1))
+            .expect("A flat T can always reach our floor");
+        game.play(&placement.piece);
+
+        let mut next = state(Tetrimino::I, Tetrimino::O);
+        next.board = game.board.clone();
+        assert_eq!(game.new_pieces(&next), Some(vec![Tetrimino::O]));
+
+        // garbage means starting over
+        next.board.set(0, 0, BoardPixel::Filled(PixelColor::Gray));
+        assert_eq!(game.new_pieces(&next), None);
+    }
+
+    #[test]
+    fn holding_with_nothing_held_plays_our_next_piece() {
+        let start = state(Tetrimino::T, Tetrimino::I);
+        let mut game = BotGame::start(&start);
+        game.play(&Piece::spawn(Tetrimino::I, 3));
+
+        assert_eq!(game.hold, Some(Tetrimino::T));
+        assert!(game.queue.is_empty());
+
+        // swapping with a held piece only uses up our current one
+        game.queue = vec![Tetrimino::O, Tetrimino::S].into();
+        game.play(&Piece::spawn(Tetrimino::T, 3));
+        assert_eq!(game.hold, Some(Tetrimino::O));
+        assert_eq!(game.queue, VecDeque::from(vec![Tetrimino::S]));
+    }
+}
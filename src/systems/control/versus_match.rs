@@ -0,0 +1,255 @@
+use amethyst::core::ecs::{Entity, World, WorldExt, WriteStorage};
+use amethyst::ui::UiText;
+use log::info;
+
+use crate::systems::control::garbage_queue::GarbageTiming;
+use crate::systems::control::margin_time::MarginTime;
+use crate::systems::ui_text::UiTextConfig;
+use crate::GameOver;
+
+// how long we show the result of a round before starting the next one
+const ROUND_RESULT_SECONDS: f32 = 3.;
+// how long we show the result of a set before heading to the results
+const SET_RESULT_SECONDS: f32 = 3.;
+
+/// How our versus games are scored
+#[derive(Copy, Clone, Debug)]
+pub struct MatchConfig {
+    /// the number of rounds in a set, the first to win most of them takes the set
+    pub best_of: usize,
+    /// how gravity and attacks ramp up the longer a round runs
+    pub margin_time: MarginTime,
+    pub garbage_timing: GarbageTiming,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        MatchConfig {
+            best_of: 3,
+            margin_time: MarginTime::default(),
+            garbage_timing: GarbageTiming::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum MatchState {
+    Playing,
+    RoundOver {
+        timer: f32,
+    },
+    /// counting down to our next round, once someone has scheduled it
+    Starting {
+        countdown: Option<f32>,
+    },
+    SetOver {
+        timer: f32,
+    },
+}
+
+/// Tracks the rounds of a versus set and shows the results between them
+pub struct VersusMatch {
+    names: Vec<String>,
+    wins: Vec<usize>,
+    rounds_to_win: usize,
+    state: MatchState,
+    result: String,
+    overlay: Entity,
+    margin_time: MarginTime,
+    garbage_timing: GarbageTiming,
+    /// seconds we've been playing this round
+    round_time: f32,
+    /// whether our next round waits for `start_in` rather than starting after our result
+    scheduled: bool,
+}
+
+impl VersusMatch {
+    pub fn new(names: Vec<String>, world: &mut World) -> VersusMatch {
+        let config = world
+            .try_fetch::<MatchConfig>()
+            .map(|config| *config)
+            .unwrap_or_default();
+
+        let overlay = UiTextConfig {
+            id: "match_result",
+            width: 1200.,
+            ..UiTextConfig::default()
+        }
+        .build("", world);
+
+        VersusMatch {
+            wins: vec![0; names.len()],
+            names,
+            rounds_to_win: config.best_of.max(1) / 2 + 1,
+            state: MatchState::Playing,
+            result: String::new(),
+            overlay,
+            margin_time: config.margin_time,
+            garbage_timing: config.garbage_timing,
+            round_time: 0.,
+            scheduled: false,
+        }
+    }
+
+    /// wait for each next round to be scheduled, so games over the network start together
+    pub fn with_scheduled_rounds(mut self) -> Self {
+        self.scheduled = true;
+        self
+    }
+
+    pub fn playing(&self) -> bool {
+        self.state == MatchState::Playing
+    }
+
+    pub fn garbage_timing(&self) -> GarbageTiming {
+        self.garbage_timing
+    }
+
+    pub fn margin_time(&self) -> &MarginTime {
+        &self.margin_time
+    }
+
+    /// whether our next round is waiting for someone to schedule it
+    pub fn waiting_to_start(&self) -> bool {
+        self.state == MatchState::Starting { countdown: None }
+    }
+
+    /// start our next round after this many seconds, unless our set is over
+    pub fn start_in(&mut self, seconds: f32) {
+        if let MatchState::RoundOver { .. } | MatchState::Starting { .. } = self.state {
+            self.state = MatchState::Starting {
+                countdown: Some(seconds),
+            };
+        }
+    }
+
+    /// how far into margin time our current round is
+    pub fn margin_step(&self) -> usize {
+        self.margin_time.step(self.round_time)
+    }
+
+    /// end the current round, `None` means nobody survived
+    pub fn round_over(&mut self, winner: Option<usize>) {
+        if !self.playing() {
+            return;
+        }
+
+        let winner = match winner {
+            Some(winner) => winner,
+            None => {
+                self.result = "Draw!".to_string();
+                self.state = MatchState::RoundOver { timer: 0. };
+                info!("{}", self.result);
+
+                return;
+            }
+        };
+
+        self.wins[winner] += 1;
+
+        let score = self
+            .wins
+            .iter()
+            .map(|wins| wins.to_string())
+            .collect::<Vec<_>>()
+            .join(" - ");
+
+        if self.wins[winner] >= self.rounds_to_win {
+            self.result = format!("{} won the set! {}", self.names[winner], score);
+            self.state = MatchState::SetOver { timer: 0. };
+        } else {
+            self.result = format!("{} won the round! {}", self.names[winner], score);
+            self.state = MatchState::RoundOver { timer: 0. };
+        }
+
+        info!("{}", self.result);
+    }
+
+    /// update our overlay, returning true when it's time to start the next round
+    pub fn update(
+        &mut self,
+        delta_seconds: f32,
+        ui_text: &mut WriteStorage<'_, UiText>,
+        game_over: &mut GameOver,
+    ) -> bool {
+        let (text, next_state) = match &mut self.state {
+            MatchState::Playing => {
+                let step = self.margin_time.step(self.round_time);
+                self.round_time += delta_seconds;
+                if self.margin_time.step(self.round_time) > step {
+                    info!("Margin time step {}", step + 1);
+                }
+
+                (String::new(), None)
+            }
+            MatchState::RoundOver { timer } => {
+                *timer += delta_seconds;
+
+                let remaining = ROUND_RESULT_SECONDS - *timer;
+                let next_state = if remaining > 0. {
+                    None
+                } else if self.scheduled {
+                    Some(MatchState::Starting { countdown: None })
+                } else {
+                    Some(MatchState::Playing)
+                };
+                (
+                    format!(
+                        "{}  Next round in {}",
+                        self.result,
+                        remaining.ceil().max(0.)
+                    ),
+                    next_state,
+                )
+            }
+            MatchState::Starting {
+                countdown: Some(countdown),
+            } => {
+                *countdown -= delta_seconds;
+
+                (
+                    format!(
+                        "{}  Next round in {}",
+                        self.result,
+                        countdown.ceil().max(1.)
+                    ),
+                    Some(MatchState::Playing).filter(|_| *countdown <= 0.),
+                )
+            }
+            MatchState::Starting { countdown: None } => {
+                (format!("{}  Waiting for the next round", self.result), None)
+            }
+            MatchState::SetOver { timer } => {
+                *timer += delta_seconds;
+
+                if *timer >= SET_RESULT_SECONDS && game_over.summary.is_none() {
+                    game_over.summary = Some(self.result.clone());
+                }
+
+                (self.result.clone(), None)
+            }
+        };
+
+        let start_next = next_state == Some(MatchState::Playing);
+        if let Some(next_state) = next_state {
+            self.state = next_state;
+        }
+        if start_next {
+            self.round_time = 0.;
+        }
+
+        if let Some(overlay) = ui_text.get_mut(self.overlay) {
+            overlay.text = if start_next { String::new() } else { text };
+        }
+
+        start_next
+    }
+}
+
+pub fn player_names(count: usize) -> Vec<String> {
+    ["Player One", "Player Two", "Player Three", "Player Four"]
+        .iter()
+        .take(count)
+        .map(|name| name.to_string())
+        .collect()
+}
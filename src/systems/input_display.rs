@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::iter;
+
+use amethyst::ecs::{Entity, World, WriteStorage};
+use amethyst::ui::{Anchor, UiText, UiTransform};
+
+use crate::events::UserInput;
+use crate::systems::tetris::{PIXEL_DIMENSION, RENDERED_BOARD_WIDTH, RENDERED_PREVIEW_WIDTH};
+use crate::systems::ui_text::UiTextConfig;
+
+// our lines sit in the column under our hold preview, working down from here above our board's bottom
+const TOP: f32 = PIXEL_DIMENSION * 9.;
+const LINE_SPACING: f32 = PIXEL_DIMENSION * 0.8;
+
+/// where our lines go beside a board, in the column under its hold preview
+pub fn beside(board: (f32, f32)) -> (f32, f32) {
+    (board.0 + RENDERED_BOARD_WIDTH + PIXEL_DIMENSION, board.1)
+}
+
+/// where our lines go on the other side of a board, as wide as the column on its right
+pub fn left_of(board: (f32, f32)) -> (f32, f32) {
+    (board.0 - PIXEL_DIMENSION - RENDERED_PREVIEW_WIDTH, board.1)
+}
+
+/// Shows a player's held actions, DAS charge and latest presses beside their board
+pub struct InputOverlay {
+    /// the bottom left of the column our lines go in, level with our board's bottom
+    position: (f32, f32),
+    lines: Vec<Entity>,
+    held: Vec<UserInput>,
+    /// side keys that have started repeating since they were pressed
+    charged: Vec<UserInput>,
+    /// our latest presses, newest first, the same press in a row is counted together
+    history: VecDeque<(UserInput, usize)>,
+    history_length: usize,
+}
+
+impl InputOverlay {
+    pub fn new(position: (f32, f32), history_length: usize, world: &mut World) -> Self {
+        let lines = (0..history_length + 2)
+            .map(|_| {
+                UiTextConfig {
+                    id: "input_display",
+                    width: RENDERED_PREVIEW_WIDTH,
+                    height: 20.,
+                    anchor: Anchor::BottomLeft,
+                    ..UiTextConfig::default()
+                }
+                .build("", world)
+            })
+            .collect();
+
+        InputOverlay {
+            position,
+            lines,
+            held: Vec::with_capacity(4),
+            charged: Vec::with_capacity(2),
+            history: VecDeque::with_capacity(history_length),
+            history_length,
+        }
+    }
+
+    pub fn pressed(&mut self, input: UserInput) {
+        if !self.held.contains(&input) {
+            self.held.push(input);
+        }
+        self.charged.retain(|&charged| charged != input);
+    }
+
+    pub fn released(&mut self, input: UserInput) {
+        self.held.retain(|&held| held != input);
+        self.charged.retain(|&charged| charged != input);
+    }
+
+    /// a press we sent along to our game
+    pub fn sent(&mut self, input: UserInput) {
+        match self.history.front_mut() {
+            Some((last, count)) if *last == input => *count += 1,
+            _ => {
+                self.history.push_front((input, 1));
+                self.history.truncate(self.history_length);
+            }
+        }
+    }
+
+    /// a held key repeated, so its DAS is charged
+    pub fn repeated(&mut self, input: UserInput) {
+        if !self.charged.contains(&input) {
+            self.charged.push(input);
+        }
+    }
+
+    /// update our lines, `das` is the side key moving us and how far it's charged out of 1
+    pub fn show(
+        &self,
+        das: Option<(UserInput, f32)>,
+        scale: f32,
+        ui_text: &mut WriteStorage<'_, UiText>,
+        ui_transforms: &mut WriteStorage<'_, UiTransform>,
+    ) {
+        let held = if self.held.is_empty() {
+            "Held  -".to_string()
+        } else {
+            let held: Vec<_> = self.held.iter().map(|&input| label(input)).collect();
+            format!("Held  {}", held.join(" "))
+        };
+
+        let das = match das {
+            Some((input, _)) if self.charged.contains(&input) => {
+                format!("DAS  {} charged", label(input))
+            }
+            Some((input, charge)) => format!("DAS  {} {:.0}%", label(input), charge * 100.),
+            None => "DAS  -".to_string(),
+        };
+
+        let history = self.history.iter().map(|&(input, count)| {
+            if count > 1 {
+                format!("{} x{}", label(input), count)
+            } else {
+                label(input).to_string()
+            }
+        });
+
+        let texts = iter::once(held)
+            .chain(iter::once(das))
+            .chain(history)
+            .chain(iter::repeat(String::new()));
+
+        for (i, (&line, text)) in self.lines.iter().zip(texts).enumerate() {
+            if let Some(ui_text) = ui_text.get_mut(line) {
+                ui_text.text = text;
+            }
+
+            // our camera can zoom out, so keep following our board
+            if let Some(transform) = ui_transforms.get_mut(line) {
+                transform.local_x = self.position.0 / scale;
+                transform.local_y =
+                    (self.position.1 + TOP - LINE_SPACING * (i as f32 + 1.)) / scale;
+            }
+        }
+    }
+}
+
+fn label(input: UserInput) -> &'static str {
+    match input {
+        UserInput::Left => "Left",
+        UserInput::Right => "Right",
+        UserInput::RotateClockwise => "Rotate",
+        UserInput::DropSoft => "Soft",
+        UserInput::DropHard => "Hard",
+        UserInput::Hold => "Hold",
+    }
+}
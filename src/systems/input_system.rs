@@ -1,68 +1,255 @@
+use std::mem;
+use std::time::Duration;
+
 use amethyst::core::Time;
-use amethyst::ecs::{Read, System, SystemData};
-use amethyst::input::InputEvent as AmethystInputEvent;
+use amethyst::ecs::{Read, ReadExpect, System, SystemData, WriteStorage};
+use amethyst::input::{InputEvent as AmethystInputEvent, InputHandler};
 use amethyst::prelude::*;
 use amethyst::shrev::{EventChannel, ReaderId};
+use amethyst::ui::{UiText, UiTransform};
+use amethyst::window::ScreenDimensions;
 use crossbeam::channel::Sender;
 use log::warn;
 
-use crate::events::UserInput;
-use crate::input::GameInput;
+use crate::events::{TimedInput, UserInput};
+use crate::input::{GameAxis, GameInput, Handling, HandlingSettings, InputDisplay, OpposingKeys};
+use crate::states::{scale_to_fit, PlayArea};
+use crate::systems::input_display::InputOverlay;
+use crate::systems::tetris::VISIBLE_WIDTH;
 use crate::systems::utils::KnownSystem;
 use crate::systems::KnownSystems;
 use crate::ExpectSender;
 
-// the official guide recommends 0.3 but that seems slow
-const REPEAT_DELAY: f32 = 0.3;
-// the official guide recommends moving across the board in 0.5 seconds but that seems slow
-const REPEAT_INTERVAL: f32 = 0.5 / 10.;
-
 pub struct InputSystem {
-    one: PlayerInput,
-    two: Option<PlayerInput>,
+    players: Vec<PlayerInput>,
     reader: ReaderId<AmethystInputEvent<GameInput>>,
+    /// the last frame we read input for, we're skipped while our game is paused or counting down
+    last_frame: Option<u64>,
 }
 
 pub struct PlayerInput {
+    handling: Handling,
     down_side_keys: Vec<(UserInput, f32)>,
-    input_tx: Sender<UserInput>,
+    /// time until our held soft drop falls again
+    soft_drop: Option<f32>,
+    /// the side our stick is pushed to, it presses and releases like a key
+    stick_side: Option<UserInput>,
+    stick_down: bool,
+    /// the moves our keys are holding that our stick can hold too, a move is only pressed by
+    /// whichever of them takes it first and released by whichever lets go last
+    held_keys: Vec<UserInput>,
+    /// the frame we're reading input for, presses land at its start and repeats when they were due
+    frame_start: Duration,
+    frame_end: Duration,
+    /// what we're showing of our hands beside our board, if anyone's watching
+    overlay: Option<InputOverlay>,
+    input_tx: Sender<TimedInput>,
 }
 
 impl PlayerInput {
     fn action_pressed(&mut self, event: UserInput) {
-        if event == UserInput::Left || event == UserInput::Right {
-            self.down_side_keys.push((event, REPEAT_DELAY))
+        if let Some(overlay) = &mut self.overlay {
+            overlay.pressed(event);
+        }
+
+        match event {
+            UserInput::Left | UserInput::Right => {
+                self.down_side_keys.push((event, self.handling.das));
+
+                if self.active_side_key().is_none() {
+                    // our other direction is holding us in place
+                    return;
+                }
+            }
+            UserInput::DropSoft => self.soft_drop = Some(self.soft_drop_interval()),
+            // give our held side keys a moment before they carry our next move along
+            UserInput::RotateClockwise | UserInput::DropHard | UserInput::Hold => {
+                let das_cut_delay = self.handling.das_cut_delay;
+                self.down_side_keys
+                    .iter_mut()
+                    .for_each(|(_, t)| *t = t.max(das_cut_delay));
+            }
         }
 
         self.send_event(event);
     }
 
+    fn key_pressed(&mut self, event: UserInput) {
+        if let UserInput::Left | UserInput::Right | UserInput::DropSoft = event {
+            let held = self.held_keys.contains(&event) || self.stick_holds(event);
+            if !self.held_keys.contains(&event) {
+                self.held_keys.push(event);
+            }
+            if held {
+                return;
+            }
+        }
+
+        self.action_pressed(event);
+    }
+
+    fn key_released(&mut self, event: UserInput) {
+        self.held_keys.retain(|&held| held != event);
+
+        if !self.stick_holds(event) {
+            self.action_released(event);
+        }
+    }
+
+    fn stick_holds(&self, event: UserInput) -> bool {
+        self.stick_side == Some(event) || (event == UserInput::DropSoft && self.stick_down)
+    }
+
     fn action_released(&mut self, event: UserInput) {
-        // retain all the keys that aren't our release event
+        if let Some(overlay) = &mut self.overlay {
+            overlay.released(event);
+        }
+
+        if event == UserInput::DropSoft {
+            self.soft_drop = None;
+        }
+
+        // retain all the keys that aren't our release event, the rest keep their charge
         self.down_side_keys.retain(|(e, _)| *e != event);
+    }
 
-        // once we release a key redo the repeat delay
-        self.down_side_keys
-            .iter_mut()
-            .for_each(|(_, t)| *t = REPEAT_DELAY);
+    /// press and release our moves as our stick leaves and comes back inside its dead zones
+    fn stick_moved(&mut self, horizontal: f32, vertical: f32) {
+        let side = if horizontal < 0. {
+            Some(UserInput::Left)
+        } else if horizontal > 0. {
+            Some(UserInput::Right)
+        } else {
+            None
+        };
+
+        if side != self.stick_side {
+            let released = mem::replace(&mut self.stick_side, side);
+            if let Some(released) = released.filter(|side| !self.held_keys.contains(side)) {
+                self.action_released(released);
+            }
+            if let Some(pressed) = side.filter(|side| !self.held_keys.contains(side)) {
+                self.action_pressed(pressed);
+            }
+        }
+
+        let down = vertical > 0.;
+        if down != self.stick_down {
+            self.stick_down = down;
+            if !self.held_keys.contains(&UserInput::DropSoft) {
+                if down {
+                    self.action_pressed(UserInput::DropSoft);
+                } else {
+                    self.action_released(UserInput::DropSoft);
+                }
+            }
+        }
+    }
+
+    /// the held side key that's moving us, if we're holding one that is
+    fn active_side_key(&self) -> Option<usize> {
+        let last = self.down_side_keys.len().checked_sub(1)?;
+
+        match self.handling.opposing_keys {
+            OpposingKeys::LastPressed => Some(last),
+            OpposingKeys::Cancel if last > 0 => None,
+            OpposingKeys::Cancel => Some(last),
+        }
+    }
+
+    /// the side key that's moving us and how far it's charged out of 1
+    fn das_charge(&self) -> Option<(UserInput, f32)> {
+        let (input, t) = self.down_side_keys[self.active_side_key()?];
+        let charge = if self.handling.das <= 0. {
+            1.
+        } else {
+            1. - t / self.handling.das
+        };
+
+        Some((input, charge))
+    }
+
+    fn start_frame(&mut self, time: &Time) {
+        self.frame_end = time.absolute_time();
+        self.frame_start = self
+            .frame_end
+            .checked_sub(time.delta_time())
+            .unwrap_or_default();
     }
 
     fn submit_down_keys(&mut self, time: &Read<'_, Time>) {
-        // check for our first down key and submit it's event
-        if !self.down_side_keys.is_empty() {
-            let i = self.down_side_keys.len() - 1;
-            self.down_side_keys[i].1 -= time.delta_seconds();
-            if self.down_side_keys[i].1 <= 0. {
-                self.send_event(self.down_side_keys[i].0);
-                self.down_side_keys[i].1 = REPEAT_INTERVAL - self.down_side_keys[i].1;
+        // every held side key charges, even while another one is moving us
+        let delta_seconds = time.delta_seconds();
+        self.down_side_keys
+            .iter_mut()
+            .for_each(|(_, t)| *t -= delta_seconds);
+
+        if let Some(index) = self.active_side_key() {
+            let (event, mut t) = self.down_side_keys[index];
+
+            if self.handling.arr <= 0. {
+                // no repeat rate means we go all the way, our game ignores moves into the wall
+                if t <= 0. {
+                    (0..VISIBLE_WIDTH).for_each(|_| self.send_due(event, -t));
+                    t = 0.;
+                }
+            } else {
+                while t <= 0. {
+                    self.send_due(event, -t);
+                    t += self.handling.arr;
+                }
             }
+
+            self.down_side_keys[index].1 = t;
         }
+
+        // a charged key waits until it's moving us again, then goes straight away
+        self.down_side_keys
+            .iter_mut()
+            .for_each(|(_, t)| *t = t.max(0.));
+
+        if let Some(mut t) = self.soft_drop {
+            t -= time.delta_seconds();
+            while t <= 0. {
+                self.send_due(UserInput::DropSoft, -t);
+                t += self.soft_drop_interval();
+            }
+            self.soft_drop = Some(t);
+        }
+    }
+
+    fn soft_drop_interval(&self) -> f32 {
+        1. / self.handling.soft_drop_factor.max(1.)
     }
 
-    fn send_event(&self, input_event: UserInput) {
-        log::trace!("forwarding message {:?}", input_event);
+    /// send an input that came in this frame
+    fn send_event(&mut self, input: UserInput) {
+        if let Some(overlay) = &mut self.overlay {
+            overlay.sent(input);
+        }
 
-        self.input_tx.send_expect(input_event);
+        self.send_at(input, self.frame_start);
+    }
+
+    /// send a repeat that came due `overdue` seconds before the end of our frame
+    fn send_due(&mut self, input: UserInput, overdue: f32) {
+        if let Some(overlay) = &mut self.overlay {
+            overlay.repeated(input);
+        }
+
+        let at = self
+            .frame_end
+            .checked_sub(Duration::from_secs_f32(overdue.max(0.)))
+            .unwrap_or_default()
+            .max(self.frame_start);
+
+        self.send_at(input, at);
+    }
+
+    fn send_at(&self, input: UserInput, at: Duration) {
+        log::trace!("forwarding message {:?} at {:?}", input, at);
+
+        self.input_tx.send_expect(TimedInput { input, at });
     }
 }
 
@@ -72,23 +259,43 @@ impl<'s> System<'s> for InputSystem {
         Read<'s, Time>,
         // get the input events
         Read<'s, EventChannel<AmethystInputEvent<GameInput>>>,
+        // our sticks' positions, their dead zones already taken out
+        Read<'s, InputHandler<GameInput>>,
+        // our input display's text and where it sits beside our boards
+        WriteStorage<'s, UiText>,
+        WriteStorage<'s, UiTransform>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, PlayArea>,
     );
 
-    fn run(&mut self, (time, input_events): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            time,
+            input_events,
+            input_handler,
+            mut ui_text,
+            mut ui_transforms,
+            dimensions,
+            play_area,
+        ) = data;
+
+        let player_count = self.players.len();
+        for player in self.players.iter_mut() {
+            player.start_frame(&time);
+        }
+
+        // presses from while we weren't running are stale, but keys let go meanwhile are still up
+        let resumed = self
+            .last_frame
+            .map_or(true, |last| time.frame_number() > last + 1);
+        self.last_frame = Some(time.frame_number());
+
         for input_event in input_events.read(&mut self.reader) {
             match input_event {
-                AmethystInputEvent::ActionPressed(action) => {
-                    let event = Into::<Option<UserInput>>::into(action);
-
-                    match (action, &mut self.two) {
-                        (_, None) if action.single() => {
-                            self.one.action_pressed(event.unwrap());
-                        }
-                        (_, Some(_)) if action.one() => {
-                            self.one.action_pressed(event.unwrap());
-                        }
-                        (_, Some(two)) if action.two() => {
-                            two.action_pressed(event.unwrap());
+                AmethystInputEvent::ActionPressed(action) if !resumed => {
+                    match (action.player(player_count), action.input()) {
+                        (Some(player), Some(event)) => {
+                            self.players[player].key_pressed(event);
                         }
                         (_, _) => {
                             warn!("Other action: {}", action);
@@ -96,17 +303,9 @@ impl<'s> System<'s> for InputSystem {
                     }
                 }
                 AmethystInputEvent::ActionReleased(action) => {
-                    let event = Into::<Option<UserInput>>::into(action);
-
-                    match (action, &mut self.two) {
-                        (_, None) if action.single() => {
-                            self.one.action_released(event.unwrap());
-                        }
-                        (_, Some(_)) if action.one() => {
-                            self.one.action_released(event.unwrap());
-                        }
-                        (_, Some(two)) if action.two() => {
-                            two.action_released(event.unwrap());
+                    match (action.player(player_count), action.input()) {
+                        (Some(player), Some(event)) => {
+                            self.players[player].key_released(event);
                         }
                         (_, _) => {
                             warn!("Other action: {}", action);
@@ -117,37 +316,74 @@ impl<'s> System<'s> for InputSystem {
             }
         }
 
-        self.one.submit_down_keys(&time);
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let (horizontal, vertical) = GameAxis::player_axes(i);
+            player.stick_moved(
+                input_handler.axis_value(&horizontal).unwrap_or(0.),
+                input_handler.axis_value(&vertical).unwrap_or(0.),
+            );
 
-        if let Some(p) = self.two.as_mut() {
-            p.submit_down_keys(&time)
+            player.submit_down_keys(&time);
+        }
+
+        let scale = scale_to_fit(&dimensions, *play_area);
+        for player in &self.players {
+            if let Some(overlay) = &player.overlay {
+                overlay.show(player.das_charge(), scale, &mut ui_text, &mut ui_transforms);
+            }
         }
     }
 }
 
 pub struct InputSystemDesc {
-    pub one_input_tx: Sender<UserInput>,
-    pub two_input_tx: Option<Sender<UserInput>>,
+    /// where to send the input for each local player, in player order
+    pub input_txs: Vec<Sender<TimedInput>>,
+    /// where each player's input display goes, see `beside` for the column next to a board
+    pub overlays: Vec<(f32, f32)>,
 }
 
 impl<'a, 'b> SystemDesc<'a, 'b, InputSystem> for InputSystemDesc {
     fn build(self, world: &mut World) -> InputSystem {
         <InputSystem as System<'_>>::SystemData::setup(world);
 
+        let settings = world
+            .try_fetch::<HandlingSettings>()
+            .map(|settings| (*settings).clone())
+            .unwrap_or_default();
+
+        let display = world
+            .try_fetch::<InputDisplay>()
+            .map(|display| *display)
+            .unwrap_or_default();
+
         let reader_id = world
             .fetch_mut::<EventChannel<AmethystInputEvent<GameInput>>>()
             .register_reader();
 
+        let overlays = self.overlays;
         InputSystem {
-            one: PlayerInput {
-                down_side_keys: Vec::with_capacity(2),
-                input_tx: self.one_input_tx,
-            },
-            two: self.two_input_tx.map(|sender| PlayerInput {
-                down_side_keys: Vec::with_capacity(2),
-                input_tx: sender,
-            }),
+            players: self
+                .input_txs
+                .into_iter()
+                .enumerate()
+                .map(|(player, input_tx)| PlayerInput {
+                    handling: settings.player(player),
+                    down_side_keys: Vec::with_capacity(2),
+                    soft_drop: None,
+                    stick_side: None,
+                    stick_down: false,
+                    held_keys: Vec::with_capacity(3),
+                    frame_start: Duration::default(),
+                    frame_end: Duration::default(),
+                    overlay: overlays
+                        .get(player)
+                        .filter(|_| display.enabled)
+                        .map(|&position| InputOverlay::new(position, display.history, world)),
+                    input_tx,
+                })
+                .collect(),
             reader: reader_id,
+            last_frame: None,
         }
     }
 }
@@ -1,12 +1,19 @@
-pub use self::control::GameType;
+pub use self::control::{
+    GameType, GarbageTiming, Hints, MarginTime, MatchConfig, SwapTrigger, TargetPolicy,
+    MAX_LOCAL_PLAYERS,
+};
 pub use self::debug_system::DebugBundle;
+pub use self::network::{NetworkAttack, NetworkMargin, NetworkPause, NetworkRound, NetworkStart};
+pub use self::tetris::{BoardState, Piece, PieceState};
 
 mod control;
 mod debug_system;
+mod input_display;
 mod input_system;
 mod network;
 mod tetris;
 
+pub mod ui_text;
 pub mod utils;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
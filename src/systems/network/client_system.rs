@@ -1,21 +1,31 @@
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 use amethyst::ecs::{Read, System, SystemData, Write};
 use amethyst::network::simulation::{NetworkSimulationEvent, TransportResource};
 use amethyst::prelude::*;
 use amethyst::shrev::{EventChannel, ReaderId};
 use crossbeam::channel::{Receiver, Sender};
-use log::error;
+use log::{error, warn};
 
-use crate::events::TetrisIn;
-use crate::systems::network::{forward_events, handle_message};
+use crate::events::{NetworkEvent, TetrisIn};
+use crate::systems::network::{
+    forward_events, handle_message, send, NetworkAttack, NetworkMargin, NetworkPause, NetworkRound,
+    NetworkStart,
+};
 use crate::systems::utils::KnownSystem;
 use crate::systems::KnownSystems;
 
+// our server may not be listening yet, say when it's still choosing a rematch, so keep telling it
+const READY_RESEND: Duration = Duration::from_secs(1);
+
 pub struct ClientSystem {
     player_out_tx: Receiver<TetrisIn>,
     opponent_in_rx: Sender<TetrisIn>,
     server_address: SocketAddr,
+    /// when we last told our server we're ready, until it schedules our start
+    ready_sent: Option<Instant>,
+    scheduled: bool,
     reader: ReaderId<NetworkSimulationEvent>,
 }
 
@@ -23,13 +33,50 @@ impl<'s> System<'s> for ClientSystem {
     type SystemData = (
         Write<'s, TransportResource>,
         Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, NetworkPause>,
+        Write<'s, NetworkStart>,
+        Write<'s, NetworkMargin>,
+        Write<'s, NetworkRound>,
+        Write<'s, NetworkAttack>,
     );
 
-    fn run(&mut self, (mut net, net_events): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut net, net_events, mut pause, mut start, mut margin, mut round, mut attack) = data;
+
+        let resend = self
+            .ready_sent
+            .map_or(true, |sent| sent.elapsed() >= READY_RESEND);
+        if !self.scheduled && resend {
+            self.ready_sent = Some(Instant::now());
+
+            send(&NetworkEvent::Ready, self.server_address, &mut net);
+        }
+
         for event in net_events.read(&mut self.reader) {
             match event {
-                NetworkSimulationEvent::Message(_addr, payload) => {
-                    handle_message(payload, &self.opponent_in_rx)
+                NetworkSimulationEvent::Message(addr, payload) => {
+                    let event = handle_message(
+                        payload,
+                        *addr,
+                        &self.opponent_in_rx,
+                        &mut pause,
+                        &mut attack,
+                        &mut net,
+                    );
+
+                    match event {
+                        Some(NetworkEvent::Ping(sample)) => {
+                            send(&NetworkEvent::Pong(sample), *addr, &mut net)
+                        }
+                        Some(NetworkEvent::StartIn(millis)) => {
+                            self.scheduled = true;
+                            start.countdown = Some(millis as f32 / 1000.)
+                        }
+                        Some(NetworkEvent::MarginStep(step)) => margin.step = step,
+                        Some(NetworkEvent::RoundOver(winner)) => round.result = Some(winner),
+                        Some(event) => warn!("Unexpected message {:?}", event),
+                        None => (),
+                    }
                 }
                 NetworkSimulationEvent::RecvError(e) => {
                     error!("Recv Error: {:?}", e);
@@ -41,7 +88,13 @@ impl<'s> System<'s> for ClientSystem {
             }
         }
 
-        forward_events(self.server_address, &self.player_out_tx, &mut net);
+        forward_events(
+            self.server_address,
+            &self.player_out_tx,
+            &mut pause,
+            &mut attack,
+            &mut net,
+        );
     }
 }
 
@@ -63,6 +116,8 @@ impl<'a, 'b> SystemDesc<'a, 'b, ClientSystem> for ClientSystemDesc {
             player_out_tx: self.player_out_tx,
             opponent_in_rx: self.opponent_in_rx,
             server_address: self.server_address,
+            ready_sent: None,
+            scheduled: false,
             reader: reader_id,
         }
     }
@@ -1,4 +1,5 @@
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use amethyst::network::simulation::{DeliveryRequirement, TransportResource, UrgencyRequirement};
 use amethyst::network::Bytes;
@@ -11,6 +12,172 @@ use crate::ExpectSender;
 pub mod client_system;
 pub mod server_system;
 
+// how long both players count down once our start is scheduled
+const COUNTDOWN_MILLIS: u32 = 3000;
+
+/// When our networked game starts, so both players count down together
+#[derive(Debug, Default)]
+pub struct NetworkStart {
+    /// seconds until we start, filled in once the server has scheduled it
+    countdown: Option<f32>,
+    /// our next round is waiting for the server to schedule it
+    requested: bool,
+}
+
+impl NetworkStart {
+    pub fn take_countdown(&mut self) -> Option<f32> {
+        self.countdown.take()
+    }
+
+    /// ask for our next round to be scheduled, only the server's requests are answered
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    fn take_request(&mut self) -> bool {
+        std::mem::replace(&mut self.requested, false)
+    }
+
+    fn schedule(&mut self, round_trip: Duration, address: SocketAddr, net: &mut TransportResource) {
+        // our client hears about the start half a round trip from now, so we wait that much longer
+        send(&NetworkEvent::StartIn(COUNTDOWN_MILLIS), address, net);
+        self.countdown = Some(COUNTDOWN_MILLIS as f32 / 1000. + round_trip.as_secs_f32() / 2.);
+    }
+}
+
+/// Our side of negotiating a pause, so neither of us keeps playing while the other is paused or gone
+#[derive(Debug, Default)]
+pub struct NetworkPause {
+    paused: bool,
+    /// what we've asked our opponent for and whether it's been sent
+    requested: Option<(bool, bool)>,
+    /// we're heading back to our menu, and whether our opponent has been told
+    leaving: Option<bool>,
+    opponent_left: bool,
+}
+
+impl NetworkPause {
+    /// whether our game should stand still, we stop as soon as we ask to pause and start again
+    /// once our opponent agrees to resume
+    pub fn stopped(&self) -> bool {
+        match self.requested {
+            Some((paused, _)) if !self.paused => paused,
+            _ => self.paused,
+        }
+    }
+
+    /// tell our opponent we're leaving, see `left`
+    pub fn leave(&mut self) {
+        self.leaving.get_or_insert(false);
+    }
+
+    /// whether our leaving has been handed to our connection
+    pub fn left(&self) -> bool {
+        self.leaving == Some(true)
+    }
+
+    pub fn opponent_left(&self) -> bool {
+        self.opponent_left
+    }
+
+    /// ask our opponent to pause or resume, nothing changes until they accept
+    pub fn request(&mut self, paused: bool) {
+        if self.paused != paused && self.requested.is_none() {
+            self.requested = Some((paused, false));
+        }
+    }
+
+    fn send_request(&mut self, address: SocketAddr, net: &mut TransportResource) {
+        if let Some((paused, sent @ false)) = &mut self.requested {
+            send(&NetworkEvent::PauseRequest(*paused), address, net);
+            *sent = true;
+        }
+
+        if let Some(sent @ false) = &mut self.leaving {
+            send(&NetworkEvent::Leave, address, net);
+            *sent = true;
+        }
+    }
+}
+
+/// How far into margin time our round is, the server decides and our client follows along
+#[derive(Debug, Default)]
+pub struct NetworkMargin {
+    step: usize,
+    /// the last step our client heard about
+    announced: usize,
+}
+
+impl NetworkMargin {
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// only the server sets our step, it goes out to the client with our next message
+    pub fn set_step(&mut self, step: usize) {
+        self.step = step;
+    }
+
+    fn announce(&mut self, address: SocketAddr, net: &mut TransportResource) {
+        if self.step != self.announced {
+            send(&NetworkEvent::MarginStep(self.step), address, net);
+            self.announced = self.step;
+        }
+    }
+}
+
+/// The garbage we trade, each side works out its own attack so its handicap, margin time and
+/// cancelling all count before it goes out
+#[derive(Debug, Default)]
+pub struct NetworkAttack {
+    /// lines we've sent that haven't gone out yet
+    outgoing: usize,
+    /// lines our opponent sent that we haven't taken yet
+    incoming: usize,
+}
+
+impl NetworkAttack {
+    pub fn send(&mut self, lines: usize) {
+        self.outgoing += lines;
+    }
+
+    pub fn take_incoming(&mut self) -> usize {
+        std::mem::replace(&mut self.incoming, 0)
+    }
+
+    fn announce(&mut self, address: SocketAddr, net: &mut TransportResource) {
+        if self.outgoing > 0 {
+            send(&NetworkEvent::Attack(self.outgoing), address, net);
+            self.outgoing = 0;
+        }
+    }
+}
+
+/// Who won our rounds, the server sees both games first hand so it decides for both of us
+#[derive(Debug, Default)]
+pub struct NetworkRound {
+    /// the winner counted from our client's side, waiting to go out on the server or to be scored on the client
+    result: Option<Option<usize>>,
+}
+
+impl NetworkRound {
+    /// only the server decides, counting from its own side where our opponent is player 1
+    pub fn decide(&mut self, winner: Option<usize>) {
+        self.result = Some(winner.map(|winner| 1 - winner));
+    }
+
+    /// the result our server sent, if one has come in since we last looked
+    pub fn take_result(&mut self) -> Option<Option<usize>> {
+        self.result.take()
+    }
+
+    fn announce(&mut self, address: SocketAddr, net: &mut TransportResource) {
+        if let Some(winner) = self.result.take() {
+            send(&NetworkEvent::RoundOver(winner), address, net);
+        }
+    }
+}
+
 pub fn send(event: &NetworkEvent, address: SocketAddr, net: &mut TransportResource) {
     let payload = encode::to_vec(&event).expect("We should always be able to serialize our events");
 
@@ -22,7 +189,15 @@ pub fn send(event: &NetworkEvent, address: SocketAddr, net: &mut TransportResour
     );
 }
 
-pub fn handle_message(payload: &Bytes, input_tx: &Sender<TetrisIn>) {
+/// handle the messages both sides care about, returning the rest
+pub fn handle_message(
+    payload: &Bytes,
+    address: SocketAddr,
+    input_tx: &Sender<TetrisIn>,
+    pause: &mut NetworkPause,
+    attack: &mut NetworkAttack,
+    net: &mut TransportResource,
+) -> Option<NetworkEvent> {
     let network_event =
         decode::from_read_ref::<_, NetworkEvent>(&payload).expect("We should only send valid data");
 
@@ -32,17 +207,45 @@ pub fn handle_message(payload: &Bytes, input_tx: &Sender<TetrisIn>) {
         NetworkEvent::GameRx(game_event) => {
             input_tx.send_expect(game_event);
         }
+        NetworkEvent::PauseRequest(paused) => {
+            // our opponent's request wins over our own
+            pause.paused = paused;
+            pause.requested = None;
+
+            send(&NetworkEvent::PauseAccept(paused), address, net);
+        }
+        NetworkEvent::PauseAccept(paused) => {
+            pause.paused = paused;
+            pause.requested = None;
+        }
+        NetworkEvent::Attack(lines) => attack.incoming += lines,
+        NetworkEvent::Leave => {
+            // there's nobody left to wait for
+            pause.paused = false;
+            pause.requested = None;
+            pause.opponent_left = true;
+        }
+        _ => return Some(network_event),
     }
+
+    None
 }
 
 pub fn forward_events(
     other_address: SocketAddr,
     output_rx: &Receiver<TetrisIn>,
+    pause: &mut NetworkPause,
+    attack: &mut NetworkAttack,
     net: &mut TransportResource,
 ) {
+    pause.send_request(other_address, net);
+
     while let Ok(rx_event) = output_rx.try_recv() {
         log::trace!("Forwarding message {:?} to {}", rx_event, other_address);
 
         send(&NetworkEvent::GameRx(rx_event), other_address, net);
     }
+
+    // our attack follows the clear that made it, so our opponent's mirror shows it first
+    attack.announce(other_address, net);
 }
@@ -1,4 +1,5 @@
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 use amethyst::ecs::{Read, System, SystemData, Write};
 use amethyst::network::simulation::{NetworkSimulationEvent, TransportResource};
@@ -6,15 +7,82 @@ use amethyst::prelude::*;
 use amethyst::shrev::{EventChannel, ReaderId};
 use crossbeam::channel::{Receiver, Sender};
 
-use crate::events::TetrisIn;
-use crate::systems::network::{forward_events, handle_message};
+use crate::events::{NetworkEvent, TetrisIn};
+use crate::systems::network::{
+    forward_events, handle_message, send, NetworkAttack, NetworkMargin, NetworkPause, NetworkRound,
+    NetworkStart,
+};
 use crate::systems::utils::KnownSystem;
 use crate::systems::KnownSystems;
 
+// how many round trips we measure before scheduling our start
+const PING_SAMPLES: usize = 5;
+
+/// Where we are in getting both players to start together
+enum Handshake {
+    WaitingForReady,
+    Measuring {
+        sent_at: Instant,
+        round_trips: Vec<Duration>,
+    },
+    /// our first round is scheduled, later ones reuse the round trip we measured for it
+    Scheduled {
+        round_trip: Duration,
+    },
+}
+
+impl Handshake {
+    fn handle(
+        &mut self,
+        event: NetworkEvent,
+        address: SocketAddr,
+        start: &mut NetworkStart,
+        net: &mut TransportResource,
+    ) {
+        match event {
+            NetworkEvent::Ready => {
+                if let Handshake::WaitingForReady = *self {
+                    *self = Handshake::Measuring {
+                        sent_at: Instant::now(),
+                        round_trips: Vec::with_capacity(PING_SAMPLES),
+                    };
+                    send(&NetworkEvent::Ping(0), address, net);
+                }
+            }
+            NetworkEvent::Pong(sample) => {
+                let round_trip = match self {
+                    Handshake::Measuring {
+                        sent_at,
+                        round_trips,
+                    } => {
+                        round_trips.push(sent_at.elapsed());
+
+                        if round_trips.len() < PING_SAMPLES {
+                            *sent_at = Instant::now();
+                            send(&NetworkEvent::Ping(sample + 1), address, net);
+                            return;
+                        }
+
+                        round_trips.iter().sum::<Duration>() / PING_SAMPLES as u32
+                    }
+                    _ => return,
+                };
+                log::info!("Round trip to {} is {:?}", address, round_trip);
+
+                start.schedule(round_trip, address, net);
+
+                *self = Handshake::Scheduled { round_trip };
+            }
+            event => log::warn!("Unexpected message {:?}", event),
+        }
+    }
+}
+
 pub struct ServerSystem {
     player_out_tx: Receiver<TetrisIn>,
     opponent_in_rx: Sender<TetrisIn>,
     client_address: Option<SocketAddr>,
+    handshake: Handshake,
     reader: ReaderId<NetworkSimulationEvent>,
 }
 
@@ -22,13 +90,29 @@ impl<'s> System<'s> for ServerSystem {
     type SystemData = (
         Write<'s, TransportResource>,
         Read<'s, EventChannel<NetworkSimulationEvent>>,
+        Write<'s, NetworkPause>,
+        Write<'s, NetworkStart>,
+        Write<'s, NetworkMargin>,
+        Write<'s, NetworkRound>,
+        Write<'s, NetworkAttack>,
     );
 
-    fn run(&mut self, (mut net, net_events): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut net, net_events, mut pause, mut start, mut margin, mut round, mut attack) = data;
+
         for event in net_events.read(&mut self.reader) {
             match event {
-                NetworkSimulationEvent::Message(_addr, payload) => {
-                    handle_message(payload, &self.opponent_in_rx)
+                NetworkSimulationEvent::Message(addr, payload) => {
+                    if let Some(event) = handle_message(
+                        payload,
+                        *addr,
+                        &self.opponent_in_rx,
+                        &mut pause,
+                        &mut attack,
+                        &mut net,
+                    ) {
+                        self.handshake.handle(event, *addr, &mut start, &mut net);
+                    }
                 }
                 NetworkSimulationEvent::Connect(addr) => {
                     log::info!("connected {}", addr);
@@ -50,7 +134,21 @@ impl<'s> System<'s> for ServerSystem {
         }
 
         if let Some(client_address) = self.client_address {
-            forward_events(client_address, &self.player_out_tx, &mut net);
+            if let Handshake::Scheduled { round_trip } = self.handshake {
+                if start.take_request() {
+                    start.schedule(round_trip, client_address, &mut net);
+                }
+            }
+
+            round.announce(client_address, &mut net);
+            margin.announce(client_address, &mut net);
+            forward_events(
+                client_address,
+                &self.player_out_tx,
+                &mut pause,
+                &mut attack,
+                &mut net,
+            );
             // while let Ok(rx_event) = self.player_out_tx.try_recv() {
             //     // if let GameTxEvent::RxEvent(rx_event) = player_output {
             //     debug!("Forwarding message {:?} to {}", rx_event, client_address);
@@ -78,6 +176,7 @@ impl<'a, 'b> SystemDesc<'a, 'b, ServerSystem> for ServerSystemDesc {
             player_out_tx: self.player_out_tx,
             opponent_in_rx: self.opponent_in_rx,
             client_address: None,
+            handshake: Handshake::WaitingForReady,
             reader: reader_id,
         }
     }
@@ -0,0 +1,42 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::systems::tetris::Tetrimino;
+
+/// A 7 piece bag, every piece shows up once before any of them repeat
+#[derive(Clone, Debug, Default)]
+pub struct PieceBag {
+    pieces: Vec<Tetrimino>,
+}
+
+impl PieceBag {
+    pub fn peek(&mut self, rng: &mut StdRng) -> Tetrimino {
+        if self.pieces.is_empty() {
+            // put all the pieces in the bag
+            self.pieces = vec![
+                Tetrimino::I,
+                Tetrimino::J,
+                Tetrimino::L,
+                Tetrimino::O,
+                Tetrimino::S,
+                Tetrimino::T,
+                Tetrimino::Z,
+            ];
+            // shuffle the bag
+            self.pieces.shuffle(rng);
+        }
+
+        *self.pieces.last().expect("Our bag is never empty here")
+    }
+
+    pub fn pop(&mut self, rng: &mut StdRng) -> Tetrimino {
+        let next = self.peek(rng);
+        self.pieces.pop();
+
+        next
+    }
+
+    pub fn clear(&mut self) {
+        self.pieces.clear();
+    }
+}
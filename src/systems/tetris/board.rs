@@ -1,17 +1,94 @@
+use std::collections::{HashSet, VecDeque};
+
 use rand::rngs::StdRng;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::events::UserInput;
 use crate::systems::tetris::{BoardPixel, Piece, PixelColor, BOARD_HEIGHT, BOARD_WIDTH};
 
+// every input that moves a piece without locking it
+const MOVEMENT_INPUTS: [UserInput; 4] = [
+    UserInput::Left,
+    UserInput::Right,
+    UserInput::RotateClockwise,
+    UserInput::DropSoft,
+];
+
+/// Somewhere a piece can lock and the shortest inputs that get it there, ending with a hard drop
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub piece: Piece,
+    pub inputs: Vec<UserInput>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Board {
-    pixels: [[BoardPixel; BOARD_WIDTH]; BOARD_HEIGHT],
+    width: usize,
+    pixels: Vec<Vec<BoardPixel>>,
 }
 
 impl Board {
     pub fn new() -> Board {
+        Board::with_width(BOARD_WIDTH)
+    }
+
+    pub fn with_width(width: usize) -> Board {
         Board {
-            pixels: [[BoardPixel::Empty; BOARD_WIDTH]; BOARD_HEIGHT],
+            width,
+            pixels: vec![vec![BoardPixel::Empty; width]; BOARD_HEIGHT],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        BOARD_HEIGHT
+    }
+
+    /// every distinct spot our piece can lock from where it is now, tucks and spins included
+    pub fn placements(&self, piece: &Piece) -> Vec<Placement> {
+        // search outwards from our piece, each position remembers how we first reached it
+        let mut positions: Vec<(Piece, Option<(usize, UserInput)>)> = vec![(piece.clone(), None)];
+        let mut visited = HashSet::new();
+        visited.insert((piece.offset, piece.orientation));
+
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+
+        let mut landings = HashSet::new();
+        let mut placements = Vec::new();
+
+        while let Some(index) = queue.pop_front() {
+            let current = positions[index].0.clone();
+
+            // we search in order of distance, so the first path to a landing is the shortest
+            let landed = current.hard_drop(self);
+            let mut pixels: Vec<_> = landed.board_pixels().collect();
+            pixels.sort_unstable();
+            if landings.insert(pixels) {
+                let mut inputs = path(&positions, index);
+                inputs.push(UserInput::DropHard);
+
+                placements.push(Placement {
+                    piece: landed,
+                    inputs,
+                });
+            }
+
+            for &input in &MOVEMENT_INPUTS {
+                if let Some(moved) = current.apply(input, self) {
+                    if visited.insert((moved.offset, moved.orientation)) {
+                        positions.push((moved, Some((index, input))));
+                        queue.push_back(positions.len() - 1);
+                    }
+                }
+            }
         }
+
+        placements
     }
 
     pub fn check_collision(&self, piece: &Piece) -> bool {
@@ -27,12 +104,15 @@ impl Board {
         false
     }
 
-    pub fn clear_row(&mut self, row_y: usize) {
-        for y in row_y..BOARD_HEIGHT - 1 {
-            self.pixels[y] = self.pixels[y + 1];
-        }
+    pub fn row_filled(&self, row_y: usize) -> bool {
+        self.pixels[row_y]
+            .iter()
+            .all(|pixel| *pixel != BoardPixel::Empty)
+    }
 
-        self.pixels[BOARD_HEIGHT - 1] = [BoardPixel::Empty; BOARD_WIDTH];
+    pub fn clear_row(&mut self, row_y: usize) {
+        self.pixels.remove(row_y);
+        self.pixels.push(vec![BoardPixel::Empty; self.width]);
     }
 
     pub fn fill_rows(&mut self, rows: usize, rng: &mut StdRng) {
@@ -40,20 +120,20 @@ impl Board {
 
         // move our rows up
         for y in (rows..BOARD_HEIGHT).rev() {
-            self.pixels[y] = self.pixels[y - rows]
+            self.pixels[y] = self.pixels[y - rows].clone()
         }
 
         // add in our filled rows
         for y in 0..rows {
-            self.pixels[y] = [BoardPixel::Filled(PixelColor::Gray); BOARD_WIDTH];
+            self.pixels[y] = vec![BoardPixel::Filled(PixelColor::Gray); self.width];
 
-            let empty_space = rng.gen_range(0, BOARD_WIDTH);
+            let empty_space = rng.gen_range(0, self.width);
             self.pixels[y][empty_space] = BoardPixel::Empty;
         }
     }
 
     pub fn get(&self, x: isize, y: isize) -> BoardPixel {
-        if Self::on_board(x, y) {
+        if self.on_board(x, y) {
             //*Index::index(Index::index(self, x as usize), y as usize)
             self.pixels[y as usize][x as usize]
         } else {
@@ -62,12 +142,126 @@ impl Board {
     }
 
     pub fn set(&mut self, x: isize, y: isize, pixel: BoardPixel) {
-        if Self::on_board(x, y) {
+        if self.on_board(x, y) {
             self.pixels[y as usize][x as usize] = pixel;
         }
     }
 
-    fn on_board(x: isize, y: isize) -> bool {
-        x >= 0 && x < BOARD_WIDTH as isize && y >= 0 && y < BOARD_HEIGHT as isize
+    fn on_board(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.width as isize && y >= 0 && y < BOARD_HEIGHT as isize
+    }
+}
+
+/// the inputs that led to one of our searched positions
+fn path(positions: &[(Piece, Option<(usize, UserInput)>)], mut index: usize) -> Vec<UserInput> {
+    let mut inputs = Vec::new();
+    while let Some((previous, input)) = positions[index].1 {
+        inputs.push(input);
+        index = previous;
+    }
+    inputs.reverse();
+
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::tetris::Tetrimino;
+
+    use super::*;
+
+    fn filled_board(filled: &[(isize, isize)]) -> Board {
+        let mut board = Board::new();
+        for &(x, y) in filled {
+            board.set(x, y, BoardPixel::Filled(PixelColor::Gray));
+        }
+
+        board
+    }
+
+    fn find(placements: &[Placement], pixels: &[(isize, isize)]) -> Option<Placement> {
+        let mut pixels = pixels.to_vec();
+        pixels.sort_unstable();
+
+        placements
+            .iter()
+            .find(|placement| {
+                let mut placed: Vec<_> = placement.piece.board_pixels().collect();
+                placed.sort_unstable();
+                placed == pixels
+            })
+            .cloned()
+    }
+
+    fn replay(piece: &Piece, inputs: &[UserInput], board: &Board) -> Piece {
+        inputs.iter().fold(piece.clone(), |piece, &input| {
+            piece
+                .apply(input, board)
+                .expect("Every input should move our piece")
+        })
+    }
+
+    #[test]
+    fn empty_board_placements() {
+        let board = Board::new();
+
+        assert_eq!(board.placements(&Piece::spawn(Tetrimino::T, 3)).len(), 34);
+        assert_eq!(board.placements(&Piece::spawn(Tetrimino::I, 3)).len(), 17);
+        assert_eq!(board.placements(&Piece::spawn(Tetrimino::O, 3)).len(), 9);
+    }
+
+    #[test]
+    fn straight_drop_is_one_input() {
+        let board = Board::new();
+        let piece = Piece::spawn(Tetrimino::T, 3);
+        let landed = piece.hard_drop(&board);
+
+        let placements = board.placements(&piece);
+        let placement = placements
+            .iter()
+            .find(|placement| placement.piece == landed)
+            .unwrap();
+
+        assert_eq!(placement.inputs, vec![UserInput::DropHard]);
+    }
+
+    #[test]
+    fn tuck_under_overhang() {
+        // a ledge over the left of our bottom row
+        let board = filled_board(&[(0, 1), (1, 1), (2, 1), (3, 1)]);
+        let piece = Piece::spawn(Tetrimino::I, 3);
+
+        let placement = find(&board.placements(&piece), &[(0, 0), (1, 0), (2, 0), (3, 0)])
+            .expect("We should be able to slide under our ledge");
+
+        assert!(placement.inputs.contains(&UserInput::DropSoft));
+        assert_eq!(
+            placement.inputs[placement.inputs.len() - 2..],
+            [UserInput::Left, UserInput::DropHard]
+        );
+        assert_eq!(replay(&piece, &placement.inputs, &board), placement.piece);
+    }
+
+    #[test]
+    fn t_spin_into_covered_slot() {
+        // a T shaped hole with its left side covered, so we have to rotate in at the bottom
+        let mut filled = vec![(3, 2), (0, 2), (1, 2), (2, 2)];
+        filled.extend((0..10).filter(|&x| x != 4).map(|x| (x, 0)));
+        filled.extend((0..10).filter(|x| !(3..=5).contains(x)).map(|x| (x, 1)));
+        let board = filled_board(&filled);
+        let piece = Piece::spawn(Tetrimino::T, 3);
+
+        let placements = board.placements(&piece);
+        let placement = find(&placements, &[(3, 1), (4, 1), (5, 1), (4, 0)])
+            .expect("We should be able to spin into our slot");
+
+        assert_eq!(
+            placement.inputs[placement.inputs.len() - 2..],
+            [UserInput::RotateClockwise, UserInput::DropHard]
+        );
+
+        for placement in placements {
+            assert_eq!(replay(&piece, &placement.inputs, &board), placement.piece);
+        }
     }
 }
@@ -0,0 +1,568 @@
+use amethyst::core::ecs::{Entity, ReadStorage, WriteStorage};
+use amethyst::ecs::{System, SystemData};
+use amethyst::prelude::*;
+use amethyst::renderer::resources::Tint;
+use amethyst::renderer::SpriteRender;
+use crossbeam::channel::{Receiver, Sender};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::events::{TetrisIn, TetrisOut, UserInput};
+use crate::sprite_loader::Sprites;
+use crate::systems::tetris::bag::PieceBag;
+use crate::systems::tetris::board::Board;
+use crate::systems::tetris::tetris_system::{
+    create_board_entity, ghost_color, render_piece, render_preview,
+};
+use crate::systems::tetris::{
+    BoardPixel, Piece, PixelColor, Rotation, Tetrimino, BOARD_HEIGHT, PIXEL_DIMENSION,
+    PREVIEW_HEIGHT, PREVIEW_WIDTH, RENDERED_BOARD_HEIGHT, RENDERED_PREVIEW_HEIGHT,
+    RENDERED_PREVIEW_WIDTH, VISIBLE_HEIGHT,
+};
+use crate::ExpectSender;
+
+pub const COOP_WIDTH: usize = 16;
+pub const RENDERED_COOP_WIDTH: f32 = PIXEL_DIMENSION * COOP_WIDTH as f32;
+
+/// where each player's pieces spawn, from left to right
+const SPAWN_COLUMNS: [isize; 2] = [2, COOP_WIDTH as isize - 6];
+
+/// Everything a single player owns on our shared board
+struct CoOpPlayer {
+    running: bool,
+    piece: Option<Piece>,
+    held_piece: Option<Tetrimino>,
+    /// tracks whether we've held a piece this drop
+    held_this_drop: bool,
+    piece_bag: PieceBag,
+    rng: StdRng,
+    spawn_column: isize,
+    in_rx: Receiver<TetrisIn>,
+    out_tx: Sender<TetrisOut>,
+    next_preview_entities: [[Entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH],
+    hold_preview_entities: [[Entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH],
+}
+
+/// A single wide board where every player controls their own falling piece
+pub struct CoOpGameSystem {
+    board: Board,
+    board_entities: Vec<Vec<Entity>>,
+    players: Vec<CoOpPlayer>,
+}
+
+impl CoOpGameSystem {
+    fn receive(&mut self, index: usize, event: TetrisIn) -> bool {
+        let valid = self.handle_event(index, &event);
+
+        if valid {
+            self.players[index]
+                .out_tx
+                .send_expect(TetrisOut::ValidIn(event));
+        }
+
+        valid
+    }
+
+    fn handle_event(&mut self, index: usize, event: &TetrisIn) -> bool {
+        match *event {
+            TetrisIn::Start(seed) => {
+                // the first player to start clears our board
+                if !self.players.iter().any(|player| player.running) {
+                    self.board = Board::with_width(COOP_WIDTH);
+                }
+
+                let player = &mut self.players[index];
+                player.running = true;
+                player.piece = None;
+                player.held_piece = None;
+                player.held_this_drop = false;
+                player.piece_bag.clear();
+                player.rng = StdRng::seed_from_u64(seed);
+
+                true
+            }
+            TetrisIn::User(input) if self.players[index].running => self.handle_input(index, input),
+            TetrisIn::Tick if self.players[index].running => self.tick(index),
+            TetrisIn::AddRows(count) if self.players[index].running => {
+                assert_ne!(count, 0);
+
+                self.board.fill_rows(count, &mut self.players[index].rng);
+                self.push_pieces_up();
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_input(&mut self, index: usize, event: UserInput) -> bool {
+        let mut piece = match self.players[index].piece.clone() {
+            Some(piece) => piece,
+            None => return false,
+        };
+
+        match event {
+            UserInput::Left | UserInput::Right => {
+                piece.offset.0 += if event == UserInput::Left { -1 } else { 1 };
+
+                if !self.collides(index, &piece) {
+                    self.players[index].piece = Some(piece);
+
+                    true
+                } else {
+                    false
+                }
+            }
+            UserInput::RotateClockwise => {
+                let rotated =
+                    piece.rotate_with(Rotation::Clockwise, |rotated| self.collides(index, rotated));
+
+                match rotated {
+                    Some(rotated_piece) => {
+                        self.players[index].piece = Some(rotated_piece);
+
+                        true
+                    }
+                    None => false,
+                }
+            }
+            UserInput::DropSoft => {
+                self.drop_piece(index, piece);
+
+                // this move is always valid
+                true
+            }
+            UserInput::DropHard => {
+                let (landed, on_board) = hard_drop(&self.board, piece, &self.other_pieces(index));
+
+                if on_board {
+                    self.lock_piece(index, landed);
+                } else {
+                    // resting on our partner's piece doesn't lock, we just wait for them
+                    self.players[index].piece = Some(landed);
+                }
+
+                // this move is always valid
+                true
+            }
+            UserInput::Hold => {
+                let player = &self.players[index];
+                if player.held_this_drop {
+                    return false;
+                }
+
+                let held_piece = player
+                    .held_piece
+                    .map(|held| Piece::spawn(held, player.spawn_column));
+                // we can't swap into a spot our partner is sitting in
+                if let Some(ref held_piece) = held_piece {
+                    if self.overlaps_other_piece(index, held_piece) {
+                        return false;
+                    }
+                }
+
+                let player = &mut self.players[index];
+                player.piece = held_piece;
+                player.held_piece = Some(piece.tetrimino);
+                player.held_this_drop = true;
+
+                true
+            }
+        }
+    }
+
+    fn tick(&mut self, index: usize) -> bool {
+        if let Some(piece) = self.players[index].piece.clone() {
+            self.drop_piece(index, piece);
+        } else {
+            let player = &mut self.players[index];
+            let next_tetrimino = player.piece_bag.peek(&mut player.rng);
+
+            let new_piece = Piece::spawn(next_tetrimino, player.spawn_column);
+            if self.board.check_collision(&new_piece) {
+                // we share a board so we also share a loss
+                for player in self.players.iter_mut() {
+                    player.running = false;
+                    player.out_tx.send_expect(TetrisOut::Lose);
+                }
+            } else if !self.overlaps_other_piece(index, &new_piece) {
+                let player = &mut self.players[index];
+                player.piece_bag.pop(&mut player.rng);
+                player.piece = Some(new_piece);
+            }
+            // otherwise our partner is in the way, so wait for them to move
+        }
+
+        true
+    }
+
+    /// move our piece down one, locking it if it lands on the board
+    fn drop_piece(&mut self, index: usize, mut piece: Piece) {
+        piece.offset.1 -= 1;
+
+        if self.board.check_collision(&piece) {
+            piece.offset.1 += 1;
+            self.lock_piece(index, piece);
+        } else if !self.overlaps_other_piece(index, &piece) {
+            self.players[index].piece = Some(piece);
+        }
+        // resting on our partner's piece doesn't lock, we just wait for them
+    }
+
+    fn lock_piece(&mut self, index: usize, piece: Piece) {
+        self.players[index]
+            .out_tx
+            .send_expect(TetrisOut::LockedPiece);
+
+        for (board_x, board_y) in piece.board_pixels() {
+            self.board.set(
+                board_x,
+                board_y,
+                BoardPixel::Filled(piece.tetrimino.color()),
+            );
+        }
+
+        // check for filled rows, these are shared by everyone
+        let mut cleared_lines = 0;
+        for y in (0..piece.bounding_box.len()).rev() {
+            let board_y = y as isize + piece.offset.1;
+
+            if board_y >= 0 && self.board.row_filled(board_y as usize) {
+                self.board.clear_row(board_y as usize);
+                cleared_lines += 1;
+            }
+        }
+
+        let player = &mut self.players[index];
+        player.piece = None;
+        player.held_this_drop = false;
+
+        if cleared_lines > 0 {
+            player
+                .out_tx
+                .send_expect(TetrisOut::RemovedRows(cleared_lines));
+        }
+
+        self.push_pieces_up();
+    }
+
+    /// when the board moves under a falling piece push it back up so it's never inside the stack
+    fn push_pieces_up(&mut self) {
+        let mut pieces: Vec<&mut Piece> = self
+            .players
+            .iter_mut()
+            .filter_map(|player| player.piece.as_mut())
+            .collect();
+
+        push_up(&self.board, &mut pieces);
+    }
+
+    fn collides(&self, index: usize, piece: &Piece) -> bool {
+        self.board.check_collision(piece) || self.overlaps_other_piece(index, piece)
+    }
+
+    fn overlaps_other_piece(&self, index: usize, piece: &Piece) -> bool {
+        overlaps(piece, &self.other_pieces(index))
+    }
+
+    /// the falling pieces of everyone but this player
+    fn other_pieces(&self, index: usize) -> Vec<&Piece> {
+        self.players
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .filter_map(|(_, player)| player.piece.as_ref())
+            .collect()
+    }
+
+    fn ghost_piece(&self, mut piece: Piece) -> Piece {
+        piece.offset.1 -= 1;
+
+        while !self.board.check_collision(&piece) {
+            piece.offset.1 -= 1;
+        }
+
+        piece.offset.1 += 1;
+
+        piece
+    }
+
+    fn board_entity(&self, x: isize, y: isize) -> Option<Entity> {
+        if x >= 0 && x < COOP_WIDTH as isize && y >= 0 && y < VISIBLE_HEIGHT as isize {
+            Some(self.board_entities[x as usize][y as usize])
+        } else {
+            None
+        }
+    }
+}
+
+fn overlaps(piece: &Piece, others: &[&Piece]) -> bool {
+    let pixels: Vec<(isize, isize)> = piece.board_pixels().collect();
+
+    others
+        .iter()
+        .any(|other| other.board_pixels().any(|pixel| pixels.contains(&pixel)))
+}
+
+/// push our pieces up out of our board, lowest first so each one is pushed out of those below it too
+fn push_up(board: &Board, pieces: &mut [&mut Piece]) {
+    pieces.sort_by_key(|piece| piece.offset.1);
+
+    for i in 0..pieces.len() {
+        let (below, rest) = pieces.split_at_mut(i);
+        let below: Vec<&Piece> = below.iter().map(|piece| &**piece).collect();
+        let piece = &mut *rest[0];
+
+        while (board.check_collision(piece) || overlaps(piece, &below))
+            && piece.offset.1 < BOARD_HEIGHT as isize
+        {
+            piece.offset.1 += 1;
+        }
+    }
+}
+
+/// drop our piece as far as it goes, returning where it stopped and whether it's resting on our board
+/// rather than on someone else's piece
+fn hard_drop(board: &Board, mut piece: Piece, others: &[&Piece]) -> (Piece, bool) {
+    loop {
+        let mut below = piece.clone();
+        below.offset.1 -= 1;
+
+        if board.check_collision(&below) {
+            return (piece, true);
+        }
+        if overlaps(&below, others) {
+            return (piece, false);
+        }
+
+        piece = below;
+    }
+}
+
+impl<'s> System<'s> for CoOpGameSystem {
+    type SystemData = WriteStorage<'s, Tint>;
+
+    fn run(&mut self, mut tint_storage: Self::SystemData) {
+        let mut any_board_changes = false;
+        for index in 0..self.players.len() {
+            while let Ok(event) = self.players[index].in_rx.try_recv() {
+                any_board_changes |= self.receive(index, event);
+            }
+        }
+
+        if !any_board_changes {
+            return;
+        }
+
+        for x in 0..COOP_WIDTH {
+            for y in 0..VISIBLE_HEIGHT {
+                let tint = tint_storage
+                    .get_mut(self.board_entities[x][y])
+                    .expect("We should always have this entity");
+
+                tint.0 = self.board.get(x as isize, y as isize).into();
+            }
+        }
+
+        // render all of our ghosts first so they never cover up a piece
+        for piece in self.players.iter().filter_map(|p| p.piece.as_ref()) {
+            let ghost = self.ghost_piece(piece.clone());
+            render_piece(
+                ghost.offset,
+                &ghost.bounding_box,
+                ghost_color(ghost.tetrimino),
+                |x, y| self.board_entity(x, y),
+                &mut tint_storage,
+            );
+        }
+
+        for piece in self.players.iter().filter_map(|p| p.piece.as_ref()) {
+            render_piece(
+                piece.offset,
+                &piece.bounding_box,
+                piece.tetrimino.color().into(),
+                |x, y| self.board_entity(x, y),
+                &mut tint_storage,
+            );
+        }
+
+        for player in self.players.iter_mut() {
+            let next_piece = player.piece_bag.peek(&mut player.rng);
+            render_preview(next_piece, &player.next_preview_entities, &mut tint_storage);
+
+            if let Some(held_piece) = player.held_piece {
+                render_preview(held_piece, &player.hold_preview_entities, &mut tint_storage);
+            }
+        }
+    }
+}
+
+pub struct CoOpGameSystemDesc {
+    pub position: (f32, f32),
+    /// the input and output channels for each player, from left to right
+    pub players: Vec<(Receiver<TetrisIn>, Sender<TetrisOut>)>,
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, CoOpGameSystem> for CoOpGameSystemDesc {
+    fn build(self, world: &mut World) -> CoOpGameSystem {
+        assert_eq!(
+            self.players.len(),
+            SPAWN_COLUMNS.len(),
+            "We only have room for two players"
+        );
+
+        // setup data we need to initialize, but not to actually run
+        <ReadStorage<'a, SpriteRender> as SystemData>::setup(&mut *world);
+
+        <CoOpGameSystem as System<'_>>::SystemData::setup(world);
+
+        let pixel_sprite = world.read_resource::<Sprites>().pixel_sprite.clone();
+
+        let dummy_entity = world.create_entity().entity;
+
+        let (x_offset, y_offset) = self.position;
+
+        debug!("loading co-op board at {}, {}", x_offset, y_offset);
+
+        let board = Board::with_width(COOP_WIDTH);
+        let mut board_entities = vec![vec![dummy_entity; VISIBLE_HEIGHT]; COOP_WIDTH];
+        for (x, column) in board_entities.iter_mut().enumerate() {
+            for (y, entity) in column.iter_mut().enumerate() {
+                *entity = create_board_entity(
+                    x,
+                    y,
+                    x_offset,
+                    y_offset,
+                    board.get(x as isize, y as isize).into(),
+                    &pixel_sprite,
+                    world,
+                );
+            }
+        }
+
+        // the left player's previews go on the left, the right player's on the right
+        let preview_x_offsets = [
+            x_offset - PIXEL_DIMENSION - RENDERED_PREVIEW_WIDTH,
+            x_offset + RENDERED_COOP_WIDTH + PIXEL_DIMENSION,
+        ];
+        let next_y_offset =
+            RENDERED_BOARD_HEIGHT - PIXEL_DIMENSION - RENDERED_PREVIEW_HEIGHT + y_offset;
+        let hold_y_offset = next_y_offset - PIXEL_DIMENSION - RENDERED_PREVIEW_HEIGHT;
+
+        let players = self
+            .players
+            .into_iter()
+            .enumerate()
+            .map(|(index, (in_rx, out_tx))| {
+                let mut next_preview_entities = [[dummy_entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH];
+                let mut hold_preview_entities = [[dummy_entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH];
+                for x in 0..PREVIEW_WIDTH {
+                    for y in 0..PREVIEW_HEIGHT {
+                        next_preview_entities[x][y] = create_board_entity(
+                            x,
+                            y,
+                            preview_x_offsets[index],
+                            next_y_offset,
+                            PixelColor::Gray.into(),
+                            &pixel_sprite,
+                            world,
+                        );
+                        hold_preview_entities[x][y] = create_board_entity(
+                            x,
+                            y,
+                            preview_x_offsets[index],
+                            hold_y_offset,
+                            PixelColor::Gray.into(),
+                            &pixel_sprite,
+                            world,
+                        );
+                    }
+                }
+
+                CoOpPlayer {
+                    running: false,
+                    piece: None,
+                    held_piece: None,
+                    held_this_drop: false,
+                    piece_bag: PieceBag::default(),
+                    rng: StdRng::seed_from_u64(0),
+                    spawn_column: SPAWN_COLUMNS[index],
+                    in_rx,
+                    out_tx,
+                    next_preview_entities,
+                    hold_preview_entities,
+                }
+            })
+            .collect();
+
+        CoOpGameSystem {
+            board,
+            board_entities,
+            players,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_pixels(piece: &Piece) -> Vec<(isize, isize)> {
+        let mut pixels: Vec<_> = piece.board_pixels().collect();
+        pixels.sort_unstable();
+
+        pixels
+    }
+
+    #[test]
+    fn pieces_overlap_when_they_share_a_pixel() {
+        let piece = Piece::new(Tetrimino::O, (2, 0));
+
+        assert!(overlaps(&piece, &[&Piece::new(Tetrimino::O, (3, 1))]));
+        assert!(!overlaps(&piece, &[&Piece::new(Tetrimino::O, (4, 0))]));
+        assert!(!overlaps(&piece, &[]));
+    }
+
+    #[test]
+    fn hard_drop_lands_on_our_board() {
+        let board = Board::with_width(COOP_WIDTH);
+        let partner = Piece::new(Tetrimino::O, (10, 0));
+
+        let (landed, on_board) = hard_drop(&board, Piece::spawn(Tetrimino::I, 2), &[&partner]);
+
+        assert!(on_board);
+        assert_eq!(sorted_pixels(&landed), vec![(2, 0), (3, 0), (4, 0), (5, 0)]);
+    }
+
+    #[test]
+    fn hard_drop_rests_on_our_partner() {
+        let board = Board::with_width(COOP_WIDTH);
+        let partner = Piece::new(Tetrimino::O, (3, 0));
+
+        let (landed, on_board) = hard_drop(&board, Piece::spawn(Tetrimino::I, 2), &[&partner]);
+
+        // we stop just above our partner without locking into the air
+        assert!(!on_board);
+        assert_eq!(sorted_pixels(&landed), vec![(2, 2), (3, 2), (4, 2), (5, 2)]);
+        assert!(!overlaps(&landed, &[&partner]));
+    }
+
+    #[test]
+    fn garbage_pushes_our_partner_up_too() {
+        let mut board = Board::with_width(COOP_WIDTH);
+        for x in 0..COOP_WIDTH as isize {
+            board.set(x, 0, BoardPixel::Filled(PixelColor::Gray));
+            board.set(x, 1, BoardPixel::Filled(PixelColor::Gray));
+        }
+
+        // our partner was resting right on top of us when two rows came in
+        let mut upper = Piece::new(Tetrimino::O, (3, 2));
+        let mut lower = Piece::new(Tetrimino::O, (3, 0));
+        push_up(&board, &mut [&mut upper, &mut lower]);
+
+        assert_eq!(lower, Piece::new(Tetrimino::O, (3, 2)));
+        assert_eq!(upper, Piece::new(Tetrimino::O, (3, 4)));
+        assert!(!overlaps(&lower, &[&upper]));
+    }
+}
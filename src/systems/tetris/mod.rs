@@ -1,11 +1,15 @@
 use amethyst::renderer::palette::Srgba;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 
+pub use board::{Board, Placement};
 pub use piece::*;
 
+mod bag;
 mod board;
 mod piece;
 
+pub mod coop_system;
 pub mod tetris_system;
 
 pub const VISIBLE_WIDTH: usize = 10;
@@ -16,10 +20,10 @@ const PREVIEW_HEIGHT: usize = 4;
 
 pub const PIXEL_DIMENSION: f32 = 50.;
 
-const RENDERED_BOARD_WIDTH: f32 = PIXEL_DIMENSION * VISIBLE_WIDTH as f32;
+pub const RENDERED_BOARD_WIDTH: f32 = PIXEL_DIMENSION * VISIBLE_WIDTH as f32;
 const RENDERED_BOARD_HEIGHT: f32 = PIXEL_DIMENSION * VISIBLE_HEIGHT as f32;
 
-const RENDERED_PREVIEW_WIDTH: f32 = PIXEL_DIMENSION * PREVIEW_WIDTH as f32;
+pub const RENDERED_PREVIEW_WIDTH: f32 = PIXEL_DIMENSION * PREVIEW_WIDTH as f32;
 const RENDERED_PREVIEW_HEIGHT: f32 = PIXEL_DIMENSION * PREVIEW_HEIGHT as f32;
 
 pub const RENDERED_WIDTH: f32 = RENDERED_BOARD_WIDTH + PIXEL_DIMENSION + RENDERED_PREVIEW_WIDTH;
@@ -28,7 +32,25 @@ pub const RENDERED_WIDTH: f32 = RENDERED_BOARD_WIDTH + PIXEL_DIMENSION + RENDERE
 const BOARD_WIDTH: usize = VISIBLE_WIDTH;
 const BOARD_HEIGHT: usize = VISIBLE_HEIGHT * 2;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// A board and the piece falling on it, so we can move them between games
+#[derive(Deserialize, Serialize, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct BoardState {
+    pub board: Board,
+    pub piece: Option<Piece>,
+}
+
+/// What a player can see of their game when a new piece shows up
+#[derive(Deserialize, Serialize, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct PieceState {
+    pub board: Board,
+    pub piece: Piece,
+    pub next: Tetrimino,
+    pub held: Option<Tetrimino>,
+    /// whether we can still hold this drop
+    pub can_hold: bool,
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Tetrimino {
     I,
     J,
@@ -113,7 +135,7 @@ impl Tetrimino {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum BoardPixel {
     Filled(PixelColor),
     Empty,
@@ -128,7 +150,7 @@ impl Into<Srgba> for BoardPixel {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum PixelColor {
     LightBlue,
     DarkBlue,
@@ -1,7 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use crate::events::UserInput;
 use crate::systems::tetris::board::Board;
 use crate::systems::tetris::Tetrimino;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Piece {
     pub offset: (isize, isize),
     pub bounding_box: Vec<Vec<bool>>,
@@ -19,6 +22,17 @@ impl Piece {
         }
     }
 
+    /// create a piece in its spawn position with its bounding box starting at `column`
+    pub fn spawn(tetrimino: Tetrimino, column: isize) -> Self {
+        let offset = match tetrimino {
+            Tetrimino::I => (column, 21 - 3),
+            Tetrimino::O => (column + 1, 22 - 2),
+            _ => (column, 22 - 3),
+        };
+
+        Piece::new(tetrimino, offset)
+    }
+
     pub fn filled_pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
         self.bounding_box
             .iter()
@@ -32,10 +46,65 @@ impl Piece {
             .flatten()
     }
 
+    /// the filled pixels of our piece in board coordinates
+    pub fn board_pixels(&self) -> impl Iterator<Item = (isize, isize)> + '_ {
+        self.filled_pixels()
+            .map(move |(x, y)| (x as isize + self.offset.0, y as isize + self.offset.1))
+    }
+
+    /// where a movement input takes us, `None` if it's blocked or doesn't move our piece
+    ///
+    /// a blocked soft drop locks our piece in a game, here it just can't move us
+    pub fn apply(&self, input: UserInput, board: &Board) -> Option<Piece> {
+        let shifted = |x, y| {
+            let mut piece = self.clone();
+            piece.offset.0 += x;
+            piece.offset.1 += y;
+
+            if board.check_collision(&piece) {
+                None
+            } else {
+                Some(piece)
+            }
+        };
+
+        match input {
+            UserInput::Left => shifted(-1, 0),
+            UserInput::Right => shifted(1, 0),
+            UserInput::DropSoft => shifted(0, -1),
+            UserInput::RotateClockwise => self.rotate(Rotation::Clockwise, board),
+            UserInput::DropHard => Some(self.hard_drop(board)),
+            UserInput::Hold => None,
+        }
+    }
+
+    /// where we land if we drop straight down
+    pub fn hard_drop(&self, board: &Board) -> Piece {
+        let mut piece = self.clone();
+        piece.offset.1 -= 1;
+
+        while !board.check_collision(&piece) {
+            piece.offset.1 -= 1;
+        }
+
+        piece.offset.1 += 1;
+
+        piece
+    }
+
     pub fn rotate(&self, rotation: Rotation, board: &Board) -> Option<Piece> {
+        self.rotate_with(rotation, |rotated_piece| {
+            board.check_collision(rotated_piece)
+        })
+    }
+
+    /// rotate using our kick table, skipping any position where `collides` is true
+    pub fn rotate_with<F>(&self, rotation: Rotation, mut collides: F) -> Option<Piece>
+    where
+        F: FnMut(&Piece) -> bool,
+    {
         self.iter_rotate(rotation)
-            .filter(|rotated_piece| !board.check_collision(&rotated_piece))
-            .next()
+            .find(|rotated_piece| !collides(rotated_piece))
     }
 
     fn iter_rotate(&self, rotation: Rotation) -> PieceRotationIter {
@@ -183,7 +252,7 @@ pub enum Rotation {
     CounterClockwise,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum Orientation {
     North,
     East,
@@ -11,22 +11,23 @@ use crossbeam::channel::Receiver;
 use crossbeam::channel::Sender;
 use log::debug;
 use rand::rngs::StdRng;
-use rand::seq::SliceRandom;
 use rand::SeedableRng;
 
 use crate::events::{TetrisIn, TetrisOut, UserInput};
 use crate::sprite_loader::Sprites;
 use crate::sprite_loader::PIXEL_DIMENSION as ACTUAL_PIXEL_DIMENSION;
 
+use crate::systems::tetris::bag::PieceBag;
 use crate::systems::tetris::board::Board;
 use crate::systems::tetris::{
-    BoardPixel, Piece, PixelColor, Rotation, Tetrimino, BOARD_WIDTH, PIXEL_DIMENSION,
+    BoardPixel, BoardState, Piece, PieceState, PixelColor, Rotation, Tetrimino, PIXEL_DIMENSION,
     PREVIEW_HEIGHT, PREVIEW_WIDTH, RENDERED_BOARD_HEIGHT, RENDERED_BOARD_WIDTH,
     RENDERED_PREVIEW_HEIGHT, VISIBLE_HEIGHT, VISIBLE_WIDTH,
 };
 use crate::ExpectSender;
 
 const RENDER_BOUNDING_BOX: bool = false;
+const SPAWN_COLUMN: isize = 3;
 
 struct UpdatedState {
     board_changed: bool,
@@ -51,6 +52,16 @@ impl UpdatedState {
 
 pub struct TetrisGameSystem {
     running: bool,
+    /// we've topped out and our board should be shown as eliminated
+    lost: bool,
+    /// our board has been taken by `TetrisIn::TakeBoard` and we're waiting for a new one
+    swapping: bool,
+    /// garbage that showed up while we were swapping boards
+    swapping_rows: usize,
+    /// garbage waiting to enter our board
+    pending_rows: usize,
+    /// where a trainer or hint wants our piece to go
+    outline: Option<Piece>,
     piece: Option<Piece>,
     held_piece: Option<Tetrimino>,
     /// tracks whether we've held a piece this drop
@@ -59,7 +70,9 @@ pub struct TetrisGameSystem {
     board_entities: [[Entity; VISIBLE_HEIGHT]; VISIBLE_WIDTH],
     next_preview_entities: [[Entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH],
     hold_preview_entities: [[Entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH],
-    piece_bag: Vec<Tetrimino>,
+    /// our pending garbage meter, in the gap between our board and our previews
+    garbage_entities: [Entity; VISIBLE_HEIGHT],
+    piece_bag: PieceBag,
     rng: StdRng,
     in_rx: Receiver<TetrisIn>,
     out_tx: Sender<TetrisOut>,
@@ -102,33 +115,98 @@ impl TetrisGameSystem {
         match event {
             TetrisIn::Start(seed) => {
                 self.running = true;
-                // clear our board
+                self.lost = false;
+                self.swapping = false;
+                self.swapping_rows = 0;
+                self.pending_rows = 0;
+                self.outline = None;
+                // clear our board and anything left over from a previous round
                 self.board = Board::new();
+                self.piece = None;
+                self.held_piece = None;
+                self.held_this_drop = false;
+                self.piece_bag.clear();
                 self.rng = StdRng::seed_from_u64(seed);
 
                 UpdatedState::input(true, TetrisIn::Start(seed))
             }
             TetrisIn::User(input) => {
-                if self.running {
+                if self.running && !self.swapping {
                     self.handle_input(input)
                 } else {
                     UpdatedState::empty()
                 }
             }
             TetrisIn::Tick => {
-                if self.running {
+                if self.running && !self.swapping {
                     self.tick()
                 } else {
                     UpdatedState::empty()
                 }
             }
             TetrisIn::AddRows(count) => {
-                if self.running {
+                if self.running && self.swapping {
+                    // hold on to these until we get our new board
+                    self.swapping_rows += count;
+
+                    UpdatedState::empty()
+                } else if self.running {
                     self.add_rows_event(count)
                 } else {
                     UpdatedState::empty()
                 }
             }
+            TetrisIn::TakeBoard => {
+                if self.running && !self.swapping {
+                    self.swapping = true;
+
+                    self.out_tx.send_expect(TetrisOut::Board(BoardState {
+                        board: self.board.clone(),
+                        piece: self.piece.take(),
+                    }));
+
+                    UpdatedState::input(true, TetrisIn::TakeBoard)
+                } else {
+                    UpdatedState::empty()
+                }
+            }
+            TetrisIn::PutBoard(state) => {
+                if self.swapping {
+                    self.swapping = false;
+                    self.board = state.board.clone();
+                    self.piece = state.piece.clone();
+                    // our outline was for the piece we handed over
+                    self.outline = None;
+                    self.send_spawned();
+
+                    let mut updated = UpdatedState::input(true, TetrisIn::PutBoard(state));
+
+                    if self.swapping_rows > 0 {
+                        let rows = self.swapping_rows;
+                        self.swapping_rows = 0;
+
+                        updated.events.extend(self.add_rows_event(rows).events);
+                    }
+
+                    updated
+                } else {
+                    UpdatedState::empty()
+                }
+            }
+            TetrisIn::PendingRows(rows) => {
+                self.pending_rows = rows;
+
+                UpdatedState::input(true, TetrisIn::PendingRows(rows))
+            }
+            TetrisIn::Outline(piece) => {
+                self.outline = piece;
+
+                // outlines are only for whoever is looking at our board, so we don't pass them on
+                UpdatedState {
+                    board_changed: true,
+                    events: vec![],
+                }
+            }
         }
     }
 
@@ -191,10 +269,11 @@ impl TetrisGameSystem {
                 }
                 UserInput::Hold => {
                     if !self.held_this_drop {
-                        self.piece = self.held_piece.map(spawn_piece);
+                        self.piece = self.held_piece.map(|held| Piece::spawn(held, SPAWN_COLUMN));
                         self.held_piece = Some(piece.tetrimino);
 
                         self.held_this_drop = true;
+                        self.send_spawned();
 
                         true
                     } else {
@@ -236,18 +315,35 @@ impl TetrisGameSystem {
         } else {
             let next_tetrimino = self.pop_next_piece();
 
-            let new_piece = spawn_piece(next_tetrimino);
+            let new_piece = Piece::spawn(next_tetrimino, SPAWN_COLUMN);
             if self.board.check_collision(&new_piece) {
                 self.running = false;
+                self.lost = true;
                 self.out_tx.send_expect(TetrisOut::Lose);
             } else {
                 self.piece = Some(new_piece);
+                self.send_spawned();
             }
         }
 
         UpdatedState::input(true, TetrisIn::Tick)
     }
 
+    /// let our player know what they're working with now that a new piece is falling
+    fn send_spawned(&mut self) {
+        if let Some(piece) = self.piece.clone() {
+            let next = self.peek_next_piece();
+
+            self.out_tx.send_expect(TetrisOut::Spawned(PieceState {
+                board: self.board.clone(),
+                piece,
+                next,
+                held: self.held_piece,
+                can_hold: !self.held_this_drop,
+            }));
+        }
+    }
+
     // fn soft_drop(&mut self, mut piece: Piece) -> bool {
     //     piece.offset.1 -= 1;
     //
@@ -276,26 +372,18 @@ impl TetrisGameSystem {
         }
 
         // check for filled rows
-        'check_row: for y in (0..piece.bounding_box.len()).rev() {
+        for y in (0..piece.bounding_box.len()).rev() {
             let board_y = y as isize + piece.offset.1;
 
             // todo do we need this check?
-            if board_y >= 0 {
-                let board_y = board_y as usize;
-
-                for x in 0..BOARD_WIDTH {
-                    if self.board.get(x as isize, board_y as isize) == BoardPixel::Empty {
-                        // we found an empty pixel so skip this row
-                        continue 'check_row;
-                    }
-                }
-
-                self.board.clear_row(board_y);
+            if board_y >= 0 && self.board.row_filled(board_y as usize) {
+                self.board.clear_row(board_y as usize);
                 cleared_lines += 1;
             }
         }
 
         self.piece = None;
+        self.outline = None;
         self.held_this_drop = false;
 
         if cleared_lines > 0 {
@@ -316,42 +404,16 @@ impl TetrisGameSystem {
         UpdatedState::input(true, TetrisIn::AddRows(count))
     }
 
-    fn drop_hard_piece(&self, mut piece: Piece) -> Piece {
-        piece.offset.1 -= 1;
-
-        while !self.board.check_collision(&piece) {
-            piece.offset.1 -= 1;
-        }
-
-        piece.offset.1 += 1;
-
-        piece
+    fn drop_hard_piece(&self, piece: Piece) -> Piece {
+        piece.hard_drop(&self.board)
     }
 
     fn peek_next_piece(&mut self) -> Tetrimino {
-        if self.piece_bag.is_empty() {
-            // put all the pieces in the bag
-            self.piece_bag = vec![
-                Tetrimino::I,
-                Tetrimino::J,
-                Tetrimino::L,
-                Tetrimino::O,
-                Tetrimino::S,
-                Tetrimino::T,
-                Tetrimino::Z,
-            ];
-            // shuffle the bag
-            self.piece_bag.shuffle(&mut self.rng);
-        }
-
-        *self.piece_bag.last().expect("Our bag is never empty here")
+        self.piece_bag.peek(&mut self.rng)
     }
 
     fn pop_next_piece(&mut self) -> Tetrimino {
-        let next = self.peek_next_piece();
-        self.piece_bag.pop();
-
-        next
+        self.piece_bag.pop(&mut self.rng)
     }
 
     fn board_entity(&self, x: isize, y: isize) -> Option<Entity> {
@@ -383,16 +445,6 @@ impl TetrisGameSystem {
     }
 }
 
-fn spawn_piece(tetrimino: Tetrimino) -> Piece {
-    let offset = match tetrimino {
-        Tetrimino::I => (3, 21 - 3),
-        Tetrimino::O => (4, 22 - 2),
-        _ => (3, 22 - 3),
-    };
-
-    Piece::new(tetrimino, offset)
-}
-
 impl<'s> System<'s> for TetrisGameSystem {
     // #[allow(clippy::type_complexity)]
     type SystemData = WriteStorage<'s, Tint>;
@@ -407,7 +459,10 @@ impl<'s> System<'s> for TetrisGameSystem {
             for x in 0..VISIBLE_WIDTH {
                 for y in 0..VISIBLE_HEIGHT {
                     let entity = self.board_entities[x][y];
-                    let tint_color = self.board.get(x as isize, y as isize).into();
+                    let mut tint_color: Srgba = self.board.get(x as isize, y as isize).into();
+                    if self.lost {
+                        tint_color = eliminated_color(tint_color);
+                    }
 
                     let tint = tint_storage
                         .get_mut(entity)
@@ -417,16 +472,17 @@ impl<'s> System<'s> for TetrisGameSystem {
                 }
             }
 
+            if let Some(ref outline) = self.outline {
+                self.render_piece(outline, outline_color(outline.tetrimino), &mut tint_storage);
+            }
+
             // render our piece
             if let Some(ref piece) = self.piece {
                 // render a ghost
                 if self.config.show_ghost {
                     let ghost = self.drop_hard_piece(piece.clone());
-                    let mut color: Hsla = Into::<Srgba>::into(ghost.tetrimino.color()).into();
-                    color.saturation *= 0.3;
-                    color.lightness *= 0.2;
 
-                    self.render_piece(&ghost, color.into(), &mut tint_storage);
+                    self.render_piece(&ghost, ghost_color(ghost.tetrimino), &mut tint_storage);
                 }
 
                 self.render_piece(piece, piece.tetrimino.color().into(), &mut tint_storage);
@@ -476,11 +532,48 @@ impl<'s> System<'s> for TetrisGameSystem {
                     render_preview(held_piece, &self.hold_preview_entities, &mut tint_storage);
                 }
             }
+
+            for (y, entity) in self.garbage_entities.iter().enumerate() {
+                let tint = tint_storage
+                    .get_mut(*entity)
+                    .expect("We should always have this entity");
+
+                tint.0 = if y < self.pending_rows {
+                    PixelColor::Red.into()
+                } else {
+                    Srgba::new(0., 0., 0., 0.)
+                };
+            }
         }
     }
 }
 
-fn render_preview(
+/// grey out a color to show that a player has been eliminated
+fn eliminated_color(color: Srgba) -> Srgba {
+    let mut color: Hsla = color.into();
+    color.saturation = 0.;
+    color.lightness *= 0.5;
+
+    color.into()
+}
+
+pub(super) fn ghost_color(tetrimino: Tetrimino) -> Srgba {
+    let mut color: Hsla = Into::<Srgba>::into(tetrimino.color()).into();
+    color.saturation *= 0.3;
+    color.lightness *= 0.2;
+
+    color.into()
+}
+
+fn outline_color(tetrimino: Tetrimino) -> Srgba {
+    let mut color: Hsla = Into::<Srgba>::into(tetrimino.color()).into();
+    color.saturation *= 0.6;
+    color.lightness *= 0.5;
+
+    color.into()
+}
+
+pub(super) fn render_preview(
     piece: Tetrimino,
     entities: &[[Entity; PREVIEW_HEIGHT]; PREVIEW_WIDTH],
     tint_storage: &mut WriteStorage<'_, Tint>,
@@ -511,7 +604,7 @@ fn render_preview(
     );
 }
 
-fn render_piece<FnEntity>(
+pub(super) fn render_piece<FnEntity>(
     offset: (isize, isize),
     bounding_box: &[Vec<bool>],
     color: Srgba,
@@ -625,8 +718,26 @@ impl<'a, 'b> SystemDesc<'a, 'b, TetrisGameSystem> for TetrisGameSystemDesc {
             }
         }
 
+        let mut garbage_entities = [dummy_entity; VISIBLE_HEIGHT];
+        for (y, entity) in garbage_entities.iter_mut().enumerate() {
+            *entity = create_board_entity(
+                VISIBLE_WIDTH,
+                y,
+                x_offset,
+                y_offset,
+                Srgba::new(0., 0., 0., 0.),
+                &pixel_sprite,
+                world,
+            );
+        }
+
         TetrisGameSystem {
             running: false,
+            lost: false,
+            swapping: false,
+            swapping_rows: 0,
+            pending_rows: 0,
+            outline: None,
             piece: None,
             held_piece: None,
             held_this_drop: false,
@@ -634,7 +745,8 @@ impl<'a, 'b> SystemDesc<'a, 'b, TetrisGameSystem> for TetrisGameSystemDesc {
             board_entities,
             next_preview_entities,
             hold_preview_entities,
-            piece_bag: vec![],
+            garbage_entities,
+            piece_bag: PieceBag::default(),
             rng: StdRng::seed_from_u64(0),
             in_rx: self.in_rx,
             out_tx: self.out_tx,
@@ -643,7 +755,7 @@ impl<'a, 'b> SystemDesc<'a, 'b, TetrisGameSystem> for TetrisGameSystemDesc {
     }
 }
 
-fn create_board_entity(
+pub(crate) fn create_board_entity(
     x: usize,
     y: usize,
     offset_x: f32,
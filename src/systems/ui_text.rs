@@ -0,0 +1,59 @@
+use amethyst::assets::{AssetStorage, Loader};
+use amethyst::ecs::prelude::{Builder, Entity, World, WorldExt};
+use amethyst::ui::{get_default_font, Anchor, FontAsset, LineMode, UiText, UiTransform};
+
+/// A convenience struct for building text that's positioned relative to the screen
+pub struct UiTextConfig {
+    pub id: &'static str,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub anchor: Anchor,
+    pub color: [f32; 4],
+}
+
+impl Default for UiTextConfig {
+    fn default() -> Self {
+        UiTextConfig {
+            id: "text",
+            x: 0.0,
+            y: 0.0,
+            width: 600.0,
+            height: 50.0,
+            anchor: Anchor::Middle,
+            color: [0., 0., 0., 1.],
+        }
+    }
+}
+
+impl UiTextConfig {
+    pub fn build(&self, text: &str, world: &mut World) -> Entity {
+        let font = get_default_font(
+            &world.read_resource::<Loader>(),
+            &world.read_resource::<AssetStorage<FontAsset>>(),
+        );
+
+        let transform = UiTransform::new(
+            self.id.to_string(),
+            self.anchor,
+            self.anchor,
+            self.x,
+            self.y,
+            1.0,
+            self.width,
+            self.height,
+        );
+
+        let ui_text = UiText::new(
+            font,
+            text.to_string(),
+            self.color,
+            self.height,
+            LineMode::Wrap,
+            Anchor::Middle,
+        );
+
+        world.create_entity().with(transform).with(ui_text).build()
+    }
+}
@@ -1,7 +1,8 @@
-use amethyst::core::HideHierarchySystem;
-use amethyst::ecs::DispatcherBuilder;
-use amethyst::ecs::System;
-use amethyst::prelude::SystemDesc;
+use amethyst::core::{ArcThreadPool, HideHierarchySystem, SystemBundle};
+use amethyst::ecs::{Dispatcher, DispatcherBuilder};
+use amethyst::ecs::{System, World};
+use amethyst::error::Error as AmethystError;
+use amethyst::prelude::{SystemDesc, WorldExt};
 use amethyst::GameDataBuilder;
 
 use crate::systems::KnownSystems;
@@ -70,6 +71,116 @@ impl<'a, 'b> WithKnownSystem<'a, 'b> for DispatcherBuilder<'a, 'b> {
     }
 }
 
+/// Builds the systems owned by one of our states, so they come and go with that state
+pub struct SystemsBuilder<'w> {
+    world: &'w mut World,
+    builder: DispatcherBuilder<'static, 'static>,
+    background: DispatcherBuilder<'static, 'static>,
+}
+
+/// The systems owned by one of our states
+pub struct StateSystems {
+    pub foreground: Dispatcher<'static, 'static>,
+    /// keeps running while other states sit on top of ours, like our network connection
+    pub background: Dispatcher<'static, 'static>,
+}
+
+impl<'w> SystemsBuilder<'w> {
+    pub fn new(world: &'w mut World) -> Self {
+        SystemsBuilder {
+            world,
+            builder: DispatcherBuilder::new(),
+            background: DispatcherBuilder::new(),
+        }
+    }
+
+    pub fn with<S>(mut self, system: S, name: &str, dependencies: &[&str]) -> Self
+    where
+        S: for<'s> System<'s> + 'static + Send,
+    {
+        self.builder.add(system, name, dependencies);
+
+        self
+    }
+
+    pub fn with_system_desc<SD, S>(self, system_desc: SD, name: &str, dependencies: &[&str]) -> Self
+    where
+        SD: SystemDesc<'static, 'static, S>,
+        S: for<'s> System<'s> + 'static + Send,
+    {
+        let system = system_desc.build(self.world);
+
+        self.with(system, name, dependencies)
+    }
+
+    pub fn with_bundle<B>(mut self, bundle: B) -> Result<Self, AmethystError>
+    where
+        B: SystemBundle<'static, 'static>,
+    {
+        bundle.build(self.world, &mut self.builder)?;
+
+        Ok(self)
+    }
+
+    pub fn with_background_bundle<B>(mut self, bundle: B) -> Result<Self, AmethystError>
+    where
+        B: SystemBundle<'static, 'static>,
+    {
+        bundle.build(self.world, &mut self.background)?;
+
+        Ok(self)
+    }
+
+    pub fn with_background_known_desc<SD, S>(mut self, system_desc: SD) -> Self
+    where
+        SD: SystemDesc<'static, 'static, S>,
+        S: for<'c> KnownSystem<'c> + 'static + Send,
+    {
+        let system = system_desc.build(self.world);
+        self.background.add_known(system);
+
+        self
+    }
+
+    pub fn build(self) -> StateSystems {
+        let pool = (*self.world.read_resource::<ArcThreadPool>()).clone();
+
+        let mut foreground = self.builder.with_pool(pool.clone()).build();
+        foreground.setup(self.world);
+
+        let mut background = self.background.with_pool(pool).build();
+        background.setup(self.world);
+
+        StateSystems {
+            foreground,
+            background,
+        }
+    }
+}
+
+impl WithKnownSystem<'static, 'static> for SystemsBuilder<'_> {
+    fn with_known<S>(mut self, system: S) -> Self
+    where
+        S: for<'s> KnownSystem<'s> + 'static + Send,
+    {
+        self.builder.add_known(system);
+
+        self
+    }
+}
+
+impl WithKnownSystemDesc<'static, 'static> for SystemsBuilder<'_> {
+    fn with_known_desc<SD, S>(self, system_desc: SD) -> Self
+    where
+        SD: SystemDesc<'static, 'static, S> + 'static,
+        S: for<'c> KnownSystem<'c> + 'static + Send,
+    {
+        let system = system_desc.build(self.world);
+
+        self.with_known(system)
+    }
+}
+
 impl KnownSystem<'_> for HideHierarchySystem {
     fn name() -> KnownSystems {
         KnownSystems::HideHierarchy